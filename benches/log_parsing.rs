@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use deposits_script::{decode_deposit_log, decode_logs_response};
+
+/// Number of deposits in the canned `eth_getLogs` response.
+const DEPOSIT_COUNT: usize = 5_000;
+
+/// Returns the hex-encoded data of a `DepositEvent` log with a distinct `index`.
+fn deposit_log_data(index: u64) -> String {
+    let mut data = vec![0u8; 576];
+    // Offsets of the five dynamic `bytes` values.
+    for (i, offset) in [160u64, 256, 320, 384, 512].iter().enumerate() {
+        data[i * 32 + 24..i * 32 + 32].copy_from_slice(&offset.to_be_bytes());
+    }
+    // Lengths of each value.
+    for (position, length) in [(160, 48u64), (256, 32), (320, 8), (384, 96), (512, 8)].iter() {
+        data[position + 24..position + 32].copy_from_slice(&length.to_be_bytes());
+    }
+    data[192..240].copy_from_slice(&[0xaa; 48]);
+    data[288..320].copy_from_slice(&[0xbb; 32]);
+    data[352..360].copy_from_slice(&32_000_000_000u64.to_le_bytes());
+    data[416..512].copy_from_slice(&[0xcc; 96]);
+    data[544..552].copy_from_slice(&index.to_le_bytes());
+    format!("0x{}", hex::encode(data))
+}
+
+/// Returns an `eth_getLogs` response body containing `count` deposit logs.
+fn logs_response(count: usize) -> Vec<u8> {
+    let logs = (0..count)
+        .map(|i| {
+            serde_json::json!({
+                "address": "0x00000000219ab540356cbb839cbe05303d7705fa",
+                "topics": [deposits_script::DEPOSIT_EVENT_TOPIC],
+                "data": deposit_log_data(i as u64),
+                "blockNumber": format!("0x{:x}", 11_184_524 + i / 10),
+                "transactionHash": format!("0x{:064x}", i),
+                "transactionIndex": "0x0",
+                "blockHash": format!("0x{:064x}", i / 10),
+                "logIndex": format!("0x{:x}", i % 10),
+                "removed": false
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": logs })
+        .to_string()
+        .into_bytes()
+}
+
+fn bench_decode_logs_response(c: &mut Criterion) {
+    let body = logs_response(DEPOSIT_COUNT);
+
+    let mut group = c.benchmark_group("decode_logs_response");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+    group.bench_function(format!("{}_deposits", DEPOSIT_COUNT), |b| {
+        b.iter(|| decode_logs_response(black_box(&body)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_decode_deposit_log(c: &mut Criterion) {
+    let logs = decode_logs_response(&logs_response(1)).unwrap();
+
+    c.bench_function("decode_deposit_log", |b| {
+        b.iter(|| decode_deposit_log(black_box(&logs[0])).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_decode_logs_response,
+    bench_decode_deposit_log
+);
+criterion_main!(benches);
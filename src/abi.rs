@@ -0,0 +1,12 @@
+//! The pieces of the Solidity ABI encoding shared by the hand-written encoders and decoders of
+//! calls to the deposit and Multicall3 contracts.
+
+/// Number of bytes in an ABI word.
+pub(crate) const WORD: usize = 32;
+
+/// Returns `value` as a big-endian ABI word.
+pub(crate) fn word(value: u64) -> [u8; WORD] {
+    let mut word = [0; WORD];
+    word[WORD - 8..].copy_from_slice(&value.to_be_bytes());
+    word
+}
@@ -0,0 +1,146 @@
+//! Fetching deposit logs with a block range which shrinks to suit the endpoint.
+
+use crate::{Eth1Client, Eth1Error, Log};
+use std::ops::Range;
+use std::time::Duration;
+
+/// The JSON-RPC error code used by geth, Infura and others for a request exceeding a limit,
+/// e.g., "query returned more than 10000 results".
+const LIMIT_EXCEEDED_CODE: i64 = -32005;
+
+/// Parts of the (lowercased) messages with which providers reject an `eth_getLogs` request for
+/// too many blocks or logs, whatever the error code.
+const TOO_LARGE_MESSAGES: &[&str] = &[
+    // geth and Infura.
+    "query returned more than",
+    // Alchemy.
+    "log response size exceeded",
+    // Ankr and others.
+    "block range is too wide",
+    // BSC and other geth forks.
+    "exceed maximum block range",
+    // QuickNode, e.g., "eth_getLogs is limited to a 10,000 range".
+    "is limited to a",
+];
+
+impl Eth1Client {
+    /// Returns the deposit logs for `address` in `block_height_range`, halving the range and
+    /// querying each half whenever a response is too large.
+    ///
+    /// A response is too large if the endpoint rejects or times out on the range (see
+    /// `is_response_too_large`), or if it contains more than the configured
+    /// `max_logs_per_response`. The latter keeps each response small and memory-bounded on
+    /// ranges with many deposits, at the cost of discarding the oversized response.
+    ///
+    /// Returns the error if a single block is still too large. The range is not split up front,
+    /// so this is best used on ranges of `Self::log_chunk_size` blocks.
+    pub async fn get_deposit_logs_adaptive(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<Vec<Log>, Eth1Error> {
+        let max_logs = self.max_logs_per_response();
+        let mut logs = vec![];
+        // Ranges still to fetch, with the earliest last so that logs are returned in order.
+        let mut pending = vec![block_height_range];
+
+        while let Some(range) = pending.pop() {
+            let can_split = range.end.saturating_sub(range.start) > 1;
+            // Each arm which does not return or continue falls through to split the range.
+            match self.get_deposit_logs_in_range(address, range.clone()).await {
+                Ok(range_logs)
+                    if can_split && max_logs.is_some_and(|max| range_logs.len() > max) => {}
+                Ok(range_logs) => {
+                    logs.extend(range_logs);
+                    continue;
+                }
+                Err(e) if can_split && is_response_too_large(&e) => {}
+                Err(e) => return Err(e),
+            }
+
+            let mid = range.start + (range.end - range.start) / 2;
+            pending.push(mid..range.end);
+            pending.push(range.start..mid);
+        }
+
+        Ok(logs)
+    }
+}
+
+/// Returns `true` if `error` suggests that a smaller block range would succeed, e.g., because
+/// the endpoint limits the number of logs in a response.
+pub fn is_response_too_large(error: &Eth1Error) -> bool {
+    match error {
+        Eth1Error::Timeout => true,
+        Eth1Error::Rpc { code, message } => {
+            let message = message.to_lowercase();
+            *code == LIMIT_EXCEEDED_CODE
+                || TOO_LARGE_MESSAGES.iter().any(|hint| message.contains(hint))
+        }
+        Eth1Error::ResponseTooLarge { .. } => true,
+        _ => false,
+    }
+}
+
+/// Returns the deposit logs for `address` in `block_height_range`, halving the range whenever a
+/// response is too large.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_logs_adaptive(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<Vec<Log>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_logs_adaptive(address, block_height_range)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc(code: i64, message: &str) -> Eth1Error {
+        Eth1Error::Rpc {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn oversized_responses_are_too_large() {
+        assert!(is_response_too_large(&Eth1Error::ResponseTooLarge {
+            limit: 1024
+        }));
+        assert!(is_response_too_large(&Eth1Error::Timeout));
+        assert!(is_response_too_large(&rpc(LIMIT_EXCEEDED_CODE, "anything")));
+    }
+
+    #[test]
+    fn provider_limit_messages_are_too_large() {
+        for message in [
+            "query returned more than 10000 results",
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range",
+            "block range is too wide",
+            "exceed maximum block range: 5000",
+            "eth_getLogs is limited to a 10,000 range",
+        ] {
+            assert!(is_response_too_large(&rpc(-32000, message)), "{}", message);
+        }
+    }
+
+    #[test]
+    fn other_errors_are_not_too_large() {
+        assert!(!is_response_too_large(&rpc(-32000, "rate limit exceeded")));
+        assert!(!is_response_too_large(&rpc(
+            -32602,
+            "invalid block range params"
+        )));
+        assert!(!is_response_too_large(&rpc(-32000, "too many requests")));
+        assert!(!is_response_too_large(&Eth1Error::InvalidResponse(
+            "exceeded".to_string()
+        )));
+        assert!(!is_response_too_large(&Eth1Error::HttpStatus(413)));
+    }
+}
@@ -0,0 +1,114 @@
+//! Bounding the memory used by concurrent `eth_getLogs` requests, by the estimated size of their
+//! responses rather than their number.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::timeout;
+
+/// How often a waiting request checks the budget again, in case it missed a wake-up because
+/// another waiter was cancelled.
+const RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A budget of response bytes shared by the requests of an `Eth1Client`, as per
+/// `ClientConfig::max_in_flight_bytes`.
+pub(crate) struct InFlightBytes {
+    limit: usize,
+    state: Mutex<BudgetState>,
+    released: Notify,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    /// The bytes reserved by requests which are in flight or whose responses are being decoded.
+    reserved: usize,
+    /// The most response bytes per block seen so far, `None` until a response has been seen.
+    bytes_per_block: Option<usize>,
+}
+
+impl InFlightBytes {
+    /// Returns a budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(BudgetState::default()),
+            released: Notify::new(),
+        }
+    }
+
+    /// Waits until the estimated response to a request for `blocks` blocks fits in the budget,
+    /// then reserves it until the returned `Reservation` is dropped.
+    ///
+    /// The estimate uses the densest response seen so far, or the whole budget if none has been
+    /// seen, so that the first request is made alone. A request is always allowed when nothing
+    /// else is reserved, even if its estimate exceeds the limit.
+    pub async fn reserve(&self, blocks: u64) -> Reservation<'_> {
+        loop {
+            if let Some(reservation) = self.try_reserve(blocks) {
+                return reservation;
+            }
+            let _ = timeout(RECHECK_INTERVAL, self.released.notified()).await;
+        }
+    }
+
+    fn try_reserve(&self, blocks: u64) -> Option<Reservation<'_>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let bytes = match state.bytes_per_block {
+            Some(per_block) => per_block.saturating_mul(blocks as usize).min(self.limit),
+            None => self.limit,
+        };
+        if state.reserved > 0 && state.reserved + bytes > self.limit {
+            return None;
+        }
+        state.reserved += bytes;
+        if state.reserved < self.limit {
+            // Pass the wake-up on, since another waiter may also fit.
+            self.released.notify();
+        }
+        Some(Reservation {
+            budget: self,
+            bytes,
+        })
+    }
+
+    fn release(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.reserved = state.reserved.saturating_sub(bytes);
+        self.released.notify();
+    }
+}
+
+/// Bytes reserved from an `InFlightBytes` budget, which are released on drop.
+pub(crate) struct Reservation<'a> {
+    budget: &'a InFlightBytes,
+    bytes: usize,
+}
+
+impl Reservation<'_> {
+    /// Replaces the estimate with the `actual` size of the response for `blocks` blocks, which
+    /// also improves the estimate for later requests.
+    pub fn record(&mut self, actual: usize, blocks: u64) {
+        let mut state = self
+            .budget
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let per_block = actual.div_ceil(blocks.max(1) as usize);
+        state.bytes_per_block = Some(
+            state
+                .bytes_per_block
+                .map_or(per_block, |b| b.max(per_block)),
+        );
+        state.reserved = state.reserved.saturating_sub(self.bytes) + actual;
+        if actual < self.bytes {
+            self.budget.released.notify();
+        }
+        self.bytes = actual;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.budget.release(self.bytes);
+    }
+}
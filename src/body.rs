@@ -0,0 +1,133 @@
+//! Reading a response body as it arrives, for parsers which can only read incrementally from a
+//! blocking `std::io::Read`, such as `serde_json::from_reader`.
+
+use futures::channel::mpsc::Receiver;
+use futures::executor::block_on;
+use futures::StreamExt;
+use std::io::{self, Read};
+
+/// The number of chunks of a body which may be received but not yet parsed, after which the
+/// receipt of further chunks waits for the parser.
+pub(crate) const BODY_CHUNKS_IN_FLIGHT: usize = 16;
+
+/// A blocking reader over the chunks of a body, as they are sent by an async task.
+///
+/// Must be read on a blocking thread (e.g., with `tokio::task::spawn_blocking`), since each read
+/// waits for the next chunk. The body ends once the sender is dropped, so a body which is cut
+/// short reads as a truncated one.
+pub(crate) struct ChunkReader<T> {
+    /// `None` once the body has ended, since the receiver must not be polled again.
+    chunks: Option<Receiver<T>>,
+    chunk: Option<T>,
+    position: usize,
+}
+
+impl<T> ChunkReader<T> {
+    pub fn new(chunks: Receiver<T>) -> Self {
+        Self {
+            chunks: Some(chunks),
+            chunk: None,
+            position: 0,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for ChunkReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(chunk) = &self.chunk {
+                let remaining = &chunk.as_ref()[self.position..];
+                if !remaining.is_empty() {
+                    let len = std::cmp::min(remaining.len(), buf.len());
+                    buf[..len].copy_from_slice(&remaining[..len]);
+                    self.position += len;
+                    return Ok(len);
+                }
+            }
+            let chunks = match &mut self.chunks {
+                Some(chunks) => chunks,
+                None => return Ok(0),
+            };
+            match block_on(chunks.next()) {
+                Some(chunk) => {
+                    self.chunk = Some(chunk);
+                    self.position = 0;
+                }
+                None => {
+                    self.chunks = None;
+                    return Ok(0);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{deposit, deposit_log_json, logs_response};
+    use crate::{decode_logs_response, logs::read_logs_response};
+    use futures::channel::mpsc;
+    use futures::SinkExt;
+    use serde_json::json;
+    use std::thread;
+
+    /// Returns a reader of `body`, sent in chunks of `chunk_size` bytes from another thread.
+    fn reader_of(body: Vec<u8>, chunk_size: usize) -> ChunkReader<Vec<u8>> {
+        let (mut sender, receiver) = mpsc::channel(1);
+        thread::spawn(move || {
+            for chunk in body.chunks(chunk_size) {
+                if block_on(sender.send(chunk.to_vec())).is_err() {
+                    return;
+                }
+            }
+        });
+        ChunkReader::new(receiver)
+    }
+
+    #[test]
+    fn reads_every_chunk_in_order() {
+        let body: Vec<u8> = (0..=255).collect();
+        let mut read = vec![];
+        reader_of(body.clone(), 7).read_to_end(&mut read).unwrap();
+        assert_eq!(read, body);
+    }
+
+    #[test]
+    fn reads_nothing_after_the_end() {
+        let mut reader = reader_of(vec![1, 2, 3], 2);
+        let mut read = vec![];
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(read, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn logs_decoded_from_chunks_match_the_whole_body() {
+        let body = logs_response(vec![
+            deposit_log_json(&deposit(0, 5), 0),
+            deposit_log_json(&deposit(1, 6), 3),
+        ]);
+        let logs = read_logs_response(reader_of(body.clone(), 7))
+            .unwrap()
+            .unwrap();
+        assert_eq!(logs, decode_logs_response(&body).unwrap());
+    }
+
+    #[test]
+    fn error_object_is_returned_in_place_of_logs() {
+        let error = json!({ "code": -32005, "message": "query returned more than 10000 results" });
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "error": error }).to_string();
+        assert_eq!(
+            read_logs_response(reader_of(body.into_bytes(), 5)).unwrap(),
+            Err(error)
+        );
+    }
+
+    #[test]
+    fn truncated_body_is_an_error() {
+        let mut body = logs_response(vec![deposit_log_json(&deposit(0, 5), 0)]);
+        body.truncate(body.len() / 2);
+        assert!(read_logs_response(reader_of(body, 7)).is_err());
+    }
+}
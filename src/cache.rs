@@ -0,0 +1,258 @@
+//! An in-memory cache of the responses to requests which can never change, e.g., for the logs
+//! of a numbered block range which is settled, so that overlapping queries do not hit the
+//! endpoint again.
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The methods whose responses are cached, provided their params identify blocks by number or
+/// hash. Other methods either depend on the head (e.g., `eth_blockNumber`) or have side effects
+/// on the endpoint (e.g., `eth_newFilter`).
+const CACHEABLE_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getCode",
+    "eth_getLogs",
+];
+
+/// Block tags, whose block changes as the chain advances.
+const BLOCK_TAGS: &[&str] = &["latest", "pending", "safe", "finalized"];
+
+/// A least-recently-used cache of response bodies, keyed by method and params.
+///
+/// Eviction scans every entry, which is fine for the few thousand entries this is meant for.
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+    /// One past the newest block which is settled, i.e., at least the follow distance behind the
+    /// head or finalized, so will not be re-orged. `0` until the head has been seen.
+    settled_end: AtomicU64,
+}
+
+/// The key of a request whose response may be cached, see `ResponseCache::key`.
+pub(crate) struct CacheKey {
+    pub key: String,
+    /// The newest block whose data the response holds, `None` if the request identifies its
+    /// block by hash, whose data never changes.
+    pub block: Option<u64>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// The response body to each request, and when it was last used.
+    entries: HashMap<String, (Vec<u8>, u64)>,
+    tick: u64,
+}
+
+impl ResponseCache {
+    /// Returns a cache holding at most `capacity` responses.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+            settled_end: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the key for a request of `method` with `params`, or `None` if its response may
+    /// change and so must not be cached.
+    ///
+    /// A response for a numbered block may still change if the chain re-orgs, so it must only be
+    /// stored once `Self::is_settled` for its `CacheKey::block`.
+    pub(crate) fn key(method: &str, params: &Value) -> Option<CacheKey> {
+        if !CACHEABLE_METHODS.contains(&method) || mentions_block_tag(params) {
+            return None;
+        }
+        let block = match method {
+            "eth_getBlockByHash" => None,
+            "eth_getBlockByNumber" => Some(hex_block(params.get(0)?)?),
+            "eth_call" | "eth_getCode" => block_param(params.get(1)?)?,
+            // A log filter without a `toBlock` is open-ended, extending to the head.
+            _ => {
+                let filter = params.get(0)?;
+                match filter.get("blockHash") {
+                    Some(_) => None,
+                    None => Some(hex_block(filter.get("toBlock")?)?),
+                }
+            }
+        };
+        Some(CacheKey {
+            key: json!([method, params]).to_string(),
+            block,
+        })
+    }
+
+    /// Records that every block up to `block` is settled, e.g., as it is the follow distance
+    /// behind the head, or finalized.
+    pub(crate) fn settle(&self, block: u64) {
+        self.settled_end
+            .fetch_max(block.saturating_add(1), Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the response to a request for `block` can no longer change, see
+    /// `CacheKey::block`.
+    pub(crate) fn is_settled(&self, block: Option<u64>) -> bool {
+        block.is_none_or(|block| block < self.settled_end.load(Ordering::SeqCst))
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().ok()?;
+        state.tick += 1;
+        let tick = state.tick;
+        state.entries.get_mut(key).map(|(body, last_used)| {
+            *last_used = tick;
+            body.clone()
+        })
+    }
+
+    /// Stores `body` as the response for `key`, evicting the least recently used response if
+    /// the cache is full.
+    ///
+    /// A `null` result is not stored, since it may only mean that the block does not exist yet.
+    pub(crate) fn insert(&self, key: String, body: Vec<u8>) {
+        if self.capacity == 0 || !has_result(&body) {
+            return;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+                let oldest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    state.entries.remove(&oldest);
+                }
+            }
+            state.tick += 1;
+            let tick = state.tick;
+            state.entries.insert(key, (body, tick));
+        }
+    }
+
+    /// Removes every response.
+    pub(crate) fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+        }
+    }
+
+    /// Returns the number of responses held.
+    pub(crate) fn len(&self) -> usize {
+        self.state
+            .lock()
+            .map(|state| state.entries.len())
+            .unwrap_or(0)
+    }
+}
+
+/// Returns `true` if any string in `value` is a block tag.
+fn mentions_block_tag(value: &Value) -> bool {
+    match value {
+        Value::String(s) => BLOCK_TAGS.contains(&s.as_str()),
+        Value::Array(values) => values.iter().any(mentions_block_tag),
+        Value::Object(fields) => fields.values().any(mentions_block_tag),
+        _ => false,
+    }
+}
+
+/// Returns the number of a `0x`-prefixed hex block number.
+fn hex_block(value: &Value) -> Option<u64> {
+    let hex = value.as_str()?.strip_prefix("0x")?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Returns the block number of a block parameter, `Some(None)` for an EIP-1898 block hash, or
+/// `None` if it is neither.
+fn block_param(value: &Value) -> Option<Option<u64>> {
+    match value.get("blockHash") {
+        Some(_) => Some(None),
+        None => hex_block(value.get("blockNumber").unwrap_or(value)).map(Some),
+    }
+}
+
+/// Returns `true` if `body` is a JSON-RPC response with a non-`null` result.
+fn has_result(body: &[u8]) -> bool {
+    // Only checks whether the `result` field is present, the rest of the body is skipped over.
+    #[derive(Deserialize)]
+    struct ResultField {
+        result: Option<IgnoredAny>,
+    }
+    serde_json::from_slice::<ResultField>(body).is_ok_and(|response| response.result.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logs_params(from: u64, to: u64) -> Value {
+        json!([{
+            "address": ["0x00000000219ab540356cbb839cbe05303d7705fa"],
+            "fromBlock": format!("0x{:x}", from),
+            "toBlock": format!("0x{:x}", to),
+        }])
+    }
+
+    #[test]
+    fn keys_record_the_newest_block() {
+        let key = ResponseCache::key("eth_getLogs", &logs_params(0x10, 0x20)).unwrap();
+        assert_eq!(key.block, Some(0x20));
+        let key = ResponseCache::key("eth_getBlockByNumber", &json!(["0x64", false])).unwrap();
+        assert_eq!(key.block, Some(100));
+        let key = ResponseCache::key("eth_call", &json!([{ "to": "0x01" }, "0x64"])).unwrap();
+        assert_eq!(key.block, Some(100));
+    }
+
+    #[test]
+    fn keys_by_hash_have_no_block() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        let call = json!([{ "to": "0x01" }, { "blockHash": hash }]);
+        assert_eq!(ResponseCache::key("eth_call", &call).unwrap().block, None);
+        let logs = json!([{ "blockHash": hash }]);
+        assert_eq!(
+            ResponseCache::key("eth_getLogs", &logs).unwrap().block,
+            None
+        );
+        let block = json!([hash, false]);
+        assert_eq!(
+            ResponseCache::key("eth_getBlockByHash", &block)
+                .unwrap()
+                .block,
+            None
+        );
+    }
+
+    #[test]
+    fn requests_which_follow_the_head_have_no_key() {
+        assert!(ResponseCache::key("eth_blockNumber", &json!([])).is_none());
+        assert!(ResponseCache::key("eth_getBlockByNumber", &json!(["latest", false])).is_none());
+        let open_ended = json!([{ "fromBlock": "0x10" }]);
+        assert!(ResponseCache::key("eth_getLogs", &open_ended).is_none());
+    }
+
+    #[test]
+    fn only_settled_blocks_are_cacheable() {
+        let cache = ResponseCache::new(10);
+        assert!(!cache.is_settled(Some(0)));
+        assert!(cache.is_settled(None));
+
+        // A head of 2100 with a follow distance of 2048.
+        let head = 2100;
+        cache.settle(head - 2048);
+        assert!(cache.is_settled(Some(52)));
+        assert!(!cache.is_settled(Some(53)));
+
+        // The range ending at the head, as requested by a sync, is not settled.
+        let key = ResponseCache::key("eth_getLogs", &logs_params(2000, head)).unwrap();
+        assert!(!cache.is_settled(key.block));
+
+        // An older head does not unsettle blocks.
+        cache.settle(10);
+        assert!(cache.is_settled(Some(52)));
+    }
+}
@@ -0,0 +1,92 @@
+//! Encoding of calls to the deposit contract's `deposit` function, e.g., to populate a deposit
+//! contract on a local test node.
+//!
+//! As with the `DepositEvent` data, every argument has a fixed length, so the layout of the
+//! calldata is hard-coded rather than produced by a general ABI encoder.
+
+use crate::abi::{word, WORD};
+use crate::deposit_log::{CREDS_LEN, PUBKEY_LEN, SIG_LEN};
+use crate::{DepositData, DEPOSIT_FN_SIGNATURE};
+use ethereum_types::U256;
+
+/// Number of wei in one gwei.
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+/// The four head words: three offsets for the `bytes` arguments and the `deposit_data_root`.
+const HEAD_LEN: usize = 4 * WORD;
+const PUBKEY_OFFSET: usize = HEAD_LEN;
+const CREDS_OFFSET: usize = PUBKEY_OFFSET + WORD + padded_len(PUBKEY_LEN);
+const SIG_OFFSET: usize = CREDS_OFFSET + WORD + padded_len(CREDS_LEN);
+
+/// Returns the `0x`-prefixed calldata for
+/// `deposit(bytes pubkey, bytes withdrawal_credentials, bytes signature, bytes32 deposit_data_root)`
+/// with the fields of `data`, and its `DepositData::tree_hash_root` as the `deposit_data_root`.
+///
+/// The amount is not part of the calldata, it is the value of the transaction, see
+/// `deposit_value_wei`.
+pub fn deposit_calldata(data: &DepositData) -> String {
+    let mut calldata = Vec::with_capacity(SIG_OFFSET + WORD + padded_len(SIG_LEN));
+    calldata.extend_from_slice(&word(PUBKEY_OFFSET as u64));
+    calldata.extend_from_slice(&word(CREDS_OFFSET as u64));
+    calldata.extend_from_slice(&word(SIG_OFFSET as u64));
+    calldata.extend_from_slice(data.tree_hash_root().as_bytes());
+    for bytes in [
+        &data.pubkey[..],
+        data.withdrawal_credentials.as_bytes(),
+        &data.signature[..],
+    ] {
+        calldata.extend_from_slice(&word(bytes.len() as u64));
+        calldata.extend_from_slice(bytes);
+        calldata.resize(calldata.len() + padded_len(bytes.len()) - bytes.len(), 0);
+    }
+
+    format!("{}{}", DEPOSIT_FN_SIGNATURE, hex::encode(calldata))
+}
+
+/// Returns the value, in wei, of a deposit of `amount` gwei.
+pub fn deposit_value_wei(amount: u64) -> U256 {
+    U256::from(amount) * U256::from(WEI_PER_GWEI)
+}
+
+/// Returns `len` rounded up to a whole number of ABI words.
+const fn padded_len(len: usize) -> usize {
+    len.div_ceil(WORD) * WORD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deposit;
+
+    #[test]
+    fn encodes_deposit_calldata() {
+        let data = DepositData::from(&deposit(0, 5));
+        let calldata = deposit_calldata(&data);
+        assert!(calldata.starts_with(DEPOSIT_FN_SIGNATURE));
+        let bytes = hex::decode(&calldata[DEPOSIT_FN_SIGNATURE.len()..]).unwrap();
+        let word_at = |offset: usize| &bytes[offset..offset + WORD];
+
+        // The head: the offsets of the `bytes` arguments, then the `deposit_data_root`.
+        assert_eq!(word_at(0), word(0x80));
+        assert_eq!(word_at(WORD), word(0xe0));
+        assert_eq!(word_at(2 * WORD), word(0x120));
+        assert_eq!(word_at(3 * WORD), data.tree_hash_root().as_bytes());
+
+        // Each argument is its length, then its bytes zero-padded to a whole number of words.
+        assert_eq!(word_at(0x80), word(48));
+        assert_eq!(&bytes[0xa0..0xd0], &[0xaa; 48][..]);
+        assert_eq!(&bytes[0xd0..0xe0], &[0; 16][..]);
+        assert_eq!(word_at(0xe0), word(32));
+        assert_eq!(word_at(0x100), &[0xbb; 32][..]);
+        assert_eq!(word_at(0x120), word(96));
+        assert_eq!(&bytes[0x140..0x1a0], &[0xcc; 96][..]);
+        assert_eq!(bytes.len(), 0x1a0);
+    }
+
+    #[test]
+    fn deposit_value_is_in_wei() {
+        assert_eq!(
+            deposit_value_wei(32_000_000_000),
+            U256::from(32u64) * U256::exp10(18)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Splitting a block range into the smaller ranges requested from the endpoint.
+
+use std::ops::Range;
+
+/// Splits the half-open `range` into consecutive half-open ranges of at most `chunk_size`
+/// blocks, covering every block of `range` exactly once.
+///
+/// The last chunk is shorter if `chunk_size` does not divide the length of `range`. An empty
+/// (or inverted) `range` yields no chunks, and a `chunk_size` of `0` is treated as `1`.
+///
+/// E.g., `chunk_range(0..5, 2) == vec![0..2, 2..4, 4..5]`
+pub fn chunk_range(range: Range<u64>, chunk_size: u64) -> Vec<Range<u64>> {
+    let chunk_size = chunk_size.max(1);
+    let mut chunks = vec![];
+
+    let mut start = range.start;
+    while start < range.end {
+        let end = std::cmp::min(start.saturating_add(chunk_size), range.end);
+        chunks.push(start..end);
+        start = end;
+    }
+
+    chunks
+}
+
+/// Returns the number of `eth_getLogs` requests needed to fetch the logs of `range` in chunks
+/// of `chunk_size` blocks, i.e., `chunk_range(range, chunk_size).len()`, without allocating the
+/// chunks.
+///
+/// Retries and the calls made around a sync (e.g., for the head block) are not included.
+pub fn estimate_request_count(range: Range<u64>, chunk_size: u64) -> usize {
+    let chunk_size = chunk_size.max(1);
+    let blocks = range.end.saturating_sub(range.start);
+    blocks.div_ceil(chunk_size) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_size_divides_range() {
+        assert_eq!(chunk_range(0..6, 2), vec![0..2, 2..4, 4..6]);
+        assert_eq!(estimate_request_count(0..6, 2), 3);
+    }
+
+    #[test]
+    fn chunk_size_does_not_divide_range() {
+        assert_eq!(chunk_range(10..17, 3), vec![10..13, 13..16, 16..17]);
+        assert_eq!(estimate_request_count(10..17, 3), 3);
+    }
+
+    #[test]
+    fn single_block_range() {
+        assert_eq!(chunk_range(5..6, 1000), vec![5..6]);
+        assert_eq!(estimate_request_count(5..6, 1000), 1);
+    }
+
+    #[test]
+    fn chunks_meet_at_boundaries() {
+        // A sync from block 100 to a head of 2100, inclusive, as with `next_block..head + 1`.
+        let chunks = chunk_range(100..2101, 1000);
+        assert_eq!(chunks, vec![100..1100, 1100..2100, 2100..2101]);
+        assert!(chunks.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.end - chunk.start)
+                .sum::<u64>(),
+            2001
+        );
+
+        // A range one block shorter or longer than a chunk.
+        assert_eq!(chunk_range(0..999, 1000), vec![0..999]);
+        assert_eq!(chunk_range(0..1001, 1000), vec![0..1000, 1000..1001]);
+    }
+
+    #[test]
+    fn empty_and_inverted_ranges() {
+        assert!(chunk_range(5..5, 10).is_empty());
+        assert_eq!(estimate_request_count(5..5, 10), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = 7..3;
+        assert!(chunk_range(inverted.clone(), 10).is_empty());
+        assert_eq!(estimate_request_count(inverted, 10), 0);
+    }
+
+    #[test]
+    fn zero_chunk_size_is_one() {
+        assert_eq!(chunk_range(0..3, 0), vec![0..1, 1..2, 2..3]);
+        assert_eq!(estimate_request_count(0..3, 0), 3);
+    }
+
+    #[test]
+    fn range_ending_at_u64_max() {
+        assert_eq!(
+            chunk_range(u64::MAX - 3..u64::MAX, 2),
+            vec![u64::MAX - 3..u64::MAX - 1, u64::MAX - 1..u64::MAX]
+        );
+    }
+}
@@ -0,0 +1,1544 @@
+//! The HTTP transport: an `Eth1Client` which holds a `reqwest` client, its configuration and the
+//! free functions which wrap it.
+
+use crate::backpressure::InFlightBytes;
+use crate::body::{ChunkReader, BODY_CHUNKS_IN_FLIGHT};
+use crate::cache::ResponseCache;
+use crate::deposit_log::check_deposit_topic;
+use crate::logs::read_logs_response;
+use crate::{
+    decode, decode_deposit_count, decode_deposit_log_with_topic, decode_deposit_root,
+    decode_logs_response, decode_logs_response_lenient, Block, BlockQuery, DepositLog, Eth1Error,
+    Eth1Id, Log, NetworkId, NetworkParams, SyncStatus, DEPOSIT_COUNT_FN_SIGNATURE,
+    DEPOSIT_EVENT_TOPIC, DEPOSIT_ROOT_FN_SIGNATURE,
+};
+use ethereum_types::H256 as Hash256;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use reqwest::{header::CONTENT_TYPE, Client, ClientBuilder, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::spawn_blocking;
+use tokio::time::{delay_for, timeout};
+
+/// Configuration for an `Eth1Client`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClientConfig {
+    /// Timeout applied to each request, unless overridden in `method_timeouts`.
+    pub timeout: Duration,
+    /// Per-method timeouts, keyed by JSON-RPC method name (e.g., `eth_getLogs`).
+    ///
+    /// Useful for allowing slow `eth_getLogs` queries without also waiting a long time for
+    /// quick calls like `eth_blockNumber`.
+    pub method_timeouts: HashMap<String, Duration>,
+    /// Aborts reading a response once its body exceeds this many bytes, with
+    /// `Eth1Error::ResponseTooLarge`.
+    ///
+    /// Protects against exhausting memory when a wide `eth_getLogs` range returns a huge
+    /// number of logs.
+    pub max_response_bytes: Option<usize>,
+    /// Rejects a request before it is sent if its serialized body exceeds this many bytes.
+    ///
+    /// Some proxies reject large POST bodies, e.g., an `eth_getLogs` filter with many addresses
+    /// or a large batch, with an unhelpful error; this gives a clearer one.
+    pub max_request_bytes: Option<usize>,
+    /// Bounds the estimated total size of the `eth_getLogs` responses in flight at once, across
+    /// all concurrent requests of the client and its clones. New requests wait while the
+    /// estimate is over the limit.
+    ///
+    /// Keeps memory bounded when fetching a log-dense range with high concurrency. Responses
+    /// are estimated from the densest range seen so far, so until the first response arrives
+    /// requests are made one at a time.
+    pub max_in_flight_bytes: Option<usize>,
+    /// Overrides the follow distance of the detected network.
+    pub follow_distance: Option<u64>,
+    /// Overrides the `eth_getLogs` chunk size of the detected network.
+    pub log_chunk_size: Option<u64>,
+    /// The widest block range allowed in a single `eth_getLogs` call.
+    ///
+    /// Ranges are checked before they are sent, which gives a clearer error than most nodes.
+    /// Local nodes generally have no limit, whilst many hosted providers reject ranges wider
+    /// than around 2,000 blocks (some allow up to 10,000, others limit the number of returned
+    /// logs instead). Consult the provider's documentation for its exact limit.
+    pub max_block_range: Option<u64>,
+    /// The most logs wanted in a single `eth_getLogs` response, beyond which
+    /// `Eth1Client::get_deposit_logs_adaptive` splits the range and queries each half.
+    pub max_logs_per_response: Option<usize>,
+    /// Controls retrying of requests which fail with a transient error.
+    pub retry: RetryConfig,
+    /// Maximum number of idle connections kept open to the endpoint. Uses the `reqwest`
+    /// default if `None`.
+    ///
+    /// When making many concurrent requests, this should be at least the level of concurrency.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept open. Uses the `reqwest` default if `None`.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Whether to set `TCP_NODELAY`, i.e., disable Nagle's algorithm, which delays small
+    /// requests. Uses the `reqwest` default (enabled) if `None`.
+    pub tcp_nodelay: Option<bool>,
+    /// The interval of TCP keep-alive probes on idle connections. Uses the `reqwest` default if
+    /// `None`.
+    pub tcp_keepalive: Option<Duration>,
+    /// Use HTTP/2 without first negotiating it with the endpoint.
+    ///
+    /// Allows many concurrent requests to be multiplexed over a single connection, but requests
+    /// will fail if the endpoint does not support HTTP/2.
+    pub http2_prior_knowledge: bool,
+    /// The `jsonrpc` field of each request. The field is omitted if `None`.
+    ///
+    /// Defaults to `"2.0"`, but some legacy endpoints want `"1.0"` or no version at all.
+    pub jsonrpc_version: Option<String>,
+    /// The number of responses to keep in an in-memory cache, which is disabled if `None`.
+    ///
+    /// Only the responses to requests for numbered blocks (or by hash) are cached, e.g., for
+    /// `eth_getLogs`, `eth_call` and `eth_getBlockByNumber`; never for `latest` or any other
+    /// tag, nor for blocks (or log ranges ending) within the follow distance of the head, unless
+    /// finalized, since these may be re-orged. Speeds up repeated audits of the same history.
+    pub response_cache_size: Option<usize>,
+    /// The topic of the deposit event of the contracts queried, for deposit-like contracts other
+    /// than the canonical deposit contract (see `DepositConfig`). Uses `DEPOSIT_EVENT_TOPIC` if
+    /// `None`.
+    pub deposit_topic: Option<String>,
+    /// Accept `eth_getLogs` responses whose result is a JSON string containing the logs, rather
+    /// than the logs themselves, see `decode_logs_response_lenient`. Off by default, since only
+    /// broken gateways need it.
+    pub lenient_results: bool,
+    /// Send pretty-printed, rather than compact, JSON request bodies. Useful when debugging, or
+    /// for proxies which are strict about the formatting of the body.
+    pub pretty_json: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(15),
+            method_timeouts: HashMap::new(),
+            max_response_bytes: None,
+            max_request_bytes: None,
+            max_in_flight_bytes: None,
+            follow_distance: None,
+            log_chunk_size: None,
+            max_block_range: None,
+            max_logs_per_response: None,
+            retry: RetryConfig::default(),
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            tcp_nodelay: None,
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+            jsonrpc_version: Some("2.0".to_string()),
+            response_cache_size: None,
+            deposit_topic: None,
+            lenient_results: false,
+            pretty_json: false,
+        }
+    }
+}
+
+/// Controls how requests which fail with a transient error (see `Eth1Error::is_transient`) are
+/// retried.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryConfig {
+    /// Maximum number of times a single request is retried. Zero disables retries.
+    pub max_retries: usize,
+    /// Delay before the first retry. The delay doubles for each subsequent retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between retries.
+    pub max_backoff: Duration,
+    /// Upper bound on the total time spent on a single request, including all attempts and the
+    /// delays between them. Once it would be exceeded, the last error is returned.
+    pub overall_deadline: Option<Duration>,
+    /// Upper bound on the total number of retries made by an `Eth1Client` and all of its
+    /// clones, e.g., across a whole sync. Once it is used up, requests fail on their first error.
+    pub retry_budget: Option<usize>,
+    /// Decides whether to retry an error, in place of `Eth1Error::is_transient`. Retries are
+    /// still bounded by `max_retries`, `overall_deadline` and `retry_budget`.
+    pub retry_on: Option<RetryPredicate>,
+}
+
+impl RetryConfig {
+    /// Returns the delay before retry number `attempt` (starting at zero).
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| {
+                std::cmp::min(backoff, self.max_backoff)
+            })
+    }
+
+    /// Returns `true` if `error` should be retried after `attempt` retries, using `retry_on` if
+    /// given.
+    fn should_retry(&self, error: &Eth1Error, attempt: usize) -> bool {
+        match &self.retry_on {
+            Some(predicate) => predicate.should_retry(error, attempt),
+            None => error.is_transient(),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            overall_deadline: None,
+            retry_budget: None,
+            retry_on: None,
+        }
+    }
+}
+
+/// The signature of a `RetryPredicate`.
+type RetryFn = dyn Fn(&Eth1Error, usize) -> bool + Send + Sync;
+
+/// A custom retry policy for `RetryConfig::retry_on`, called with the error and the number of
+/// retries made so far.
+///
+/// E.g., `RetryPredicate::new(|e, _| e.is_transient() || e.to_string().contains("header not
+/// found"))` also retries a provider error which is usually transient.
+#[derive(Clone)]
+pub struct RetryPredicate(Arc<RetryFn>);
+
+impl RetryPredicate {
+    pub fn new<F: Fn(&Eth1Error, usize) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        Self(Arc::new(predicate))
+    }
+
+    /// Returns `true` if `error` should be retried after `attempt` retries.
+    pub fn should_retry(&self, error: &Eth1Error, attempt: usize) -> bool {
+        (self.0)(error, attempt)
+    }
+}
+
+impl fmt::Debug for RetryPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RetryPredicate")
+    }
+}
+
+/// Predicates are equal only if they are clones of each other.
+impl PartialEq for RetryPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The JSON-RPC method used to detect the network of an `Eth1Client`.
+///
+/// The network id returned by `net_version` is the same as the chain id on most networks, but
+/// not all (e.g., Ethereum Classic), so an id from `NetVersion` may not be the chain id.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NetworkSource {
+    /// `eth_chainId`, as specified by EIP-695.
+    ChainId,
+    /// `net_version`, used when the endpoint is too old to provide `eth_chainId`.
+    NetVersion,
+}
+
+/// A handle to an eth1 HTTP JSON-RPC endpoint.
+///
+/// Holds a single `reqwest` client, so connections are reused across requests made through the
+/// same `Eth1Client`.
+#[derive(Clone)]
+pub struct Eth1Client {
+    http: Client,
+    endpoint: String,
+    config: ClientConfig,
+    network: Option<Eth1Id>,
+    network_source: Option<NetworkSource>,
+    /// The number of retries made so far, shared between clones.
+    retries: Arc<AtomicUsize>,
+    /// The widest `eth_getLogs` range found by `Self::detect_max_block_range`, or zero if it
+    /// has not been run. Shared between clones.
+    detected_max_block_range: Arc<AtomicU64>,
+    /// Responses cached as per `ClientConfig::response_cache_size`, shared between clones.
+    cache: Option<Arc<ResponseCache>>,
+    /// The budget of `ClientConfig::max_in_flight_bytes`, shared between clones.
+    in_flight: Option<Arc<InFlightBytes>>,
+    /// The requests in flight, and whether `Self::shutdown` has been called. Shared between
+    /// clones.
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Eth1Client {
+    /// Creates a client for `endpoint` without contacting it.
+    ///
+    /// The network is unknown until detected, see `Self::connect`.
+    pub fn new(endpoint: &str, timeout: Duration) -> Self {
+        Self::with_config(
+            endpoint,
+            ClientConfig {
+                timeout,
+                ..ClientConfig::default()
+            },
+        )
+    }
+
+    /// Creates a client for `endpoint` using `config`, without contacting it.
+    pub fn with_config(endpoint: &str, config: ClientConfig) -> Self {
+        let mut builder = ClientBuilder::new().timeout(config.timeout);
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = config.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(enabled) = config.tcp_nodelay {
+            builder = builder.tcp_nodelay_(enabled);
+        }
+        if let Some(interval) = config.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        let http = builder
+            .build()
+            .expect("The builder should always build a client");
+        Self::from_parts(http, endpoint, config)
+    }
+
+    /// Creates a client for `endpoint` which makes its requests with an existing `reqwest`
+    /// client, e.g., one shared with the rest of an application.
+    ///
+    /// The `timeout` is applied to each request. The connection pool, TCP and HTTP/2 settings of
+    /// `ClientConfig` have no effect, since they are properties of `http`.
+    pub fn from_reqwest(http: Client, endpoint: &str, timeout: Duration) -> Self {
+        Self::from_parts(
+            http,
+            endpoint,
+            ClientConfig {
+                timeout,
+                ..ClientConfig::default()
+            },
+        )
+    }
+
+    fn from_parts(http: Client, endpoint: &str, config: ClientConfig) -> Self {
+        let cache = config
+            .response_cache_size
+            .map(|size| Arc::new(ResponseCache::new(size)));
+        let in_flight = config
+            .max_in_flight_bytes
+            .map(|limit| Arc::new(InFlightBytes::new(limit)));
+        Self {
+            http,
+            endpoint: endpoint.to_string(),
+            config,
+            network: None,
+            network_source: None,
+            retries: Arc::new(AtomicUsize::new(0)),
+            detected_max_block_range: Arc::new(AtomicU64::new(0)),
+            cache,
+            in_flight,
+            lifecycle: Arc::new(Lifecycle::default()),
+        }
+    }
+
+    /// Creates a client for `endpoint` and detects its network using `eth_chainId`, falling back
+    /// to `net_version` if the endpoint does not provide `eth_chainId`. See
+    /// `Self::network_source`.
+    ///
+    /// Returns an error if the endpoint is unreachable or does not return a valid id.
+    pub async fn connect(endpoint: &str, timeout: Duration) -> Result<Self, Eth1Error> {
+        Self::new(endpoint, timeout).detect_network().await
+    }
+
+    /// As per `Self::connect`, but using `config`.
+    pub async fn connect_with_config(
+        endpoint: &str,
+        config: ClientConfig,
+    ) -> Result<Self, Eth1Error> {
+        Self::with_config(endpoint, config).detect_network().await
+    }
+
+    async fn detect_network(mut self) -> Result<Self, Eth1Error> {
+        let (network, source) = match self.get_chain_id().await {
+            Ok(id) => (id, NetworkSource::ChainId),
+            Err(e) if e.is_method_not_found() => (
+                Eth1Id::from(self.get_network_id().await?.0),
+                NetworkSource::NetVersion,
+            ),
+            Err(e) => return Err(e),
+        };
+        self.network = Some(network);
+        self.network_source = Some(source);
+        Ok(self)
+    }
+
+    /// The URL of the eth1 endpoint. E.g., `http://localhost:8545`.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The request timeout this client was created with.
+    pub fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+
+    /// The timeout for requests of the given `method`.
+    pub fn timeout_for(&self, method: &str) -> Duration {
+        self.config
+            .method_timeouts
+            .get(method)
+            .copied()
+            .unwrap_or(self.config.timeout)
+    }
+
+    /// The topic of the deposit logs requested by this client, `DEPOSIT_EVENT_TOPIC` unless
+    /// configured otherwise.
+    pub fn deposit_topic(&self) -> &str {
+        self.config
+            .deposit_topic
+            .as_deref()
+            .unwrap_or(DEPOSIT_EVENT_TOPIC)
+    }
+
+    /// The network detected by `Self::connect`, if any.
+    pub fn network(&self) -> Option<&Eth1Id> {
+        self.network.as_ref()
+    }
+
+    /// The method which `Self::network` was detected with, if any.
+    pub fn network_source(&self) -> Option<NetworkSource> {
+        self.network_source
+    }
+
+    /// The default parameters of the detected network, or conservative defaults if the network
+    /// is unknown.
+    pub fn network_params(&self) -> NetworkParams {
+        self.network
+            .as_ref()
+            .map(NetworkParams::for_network)
+            .unwrap_or_default()
+    }
+
+    /// The follow distance from the config, falling back to the network default.
+    pub fn follow_distance(&self) -> u64 {
+        self.config
+            .follow_distance
+            .unwrap_or_else(|| self.network_params().follow_distance)
+    }
+
+    /// The `eth_getLogs` chunk size from the config, falling back to the network default.
+    ///
+    /// Never exceeds the configured `max_block_range`, nor the limit found by
+    /// `Self::detect_max_block_range`.
+    pub fn log_chunk_size(&self) -> u64 {
+        let chunk_size = self
+            .config
+            .log_chunk_size
+            .unwrap_or_else(|| self.network_params().log_chunk_size);
+        [self.config.max_block_range, self.detected_max_block_range()]
+            .iter()
+            .flatten()
+            .fold(chunk_size, |chunk_size, max| {
+                std::cmp::min(chunk_size, *max)
+            })
+    }
+
+    /// The widest `eth_getLogs` range allowed by the endpoint, if found by
+    /// `Self::detect_max_block_range`.
+    pub fn detected_max_block_range(&self) -> Option<u64> {
+        match self.detected_max_block_range.load(Ordering::Relaxed) {
+            0 => None,
+            max => Some(max),
+        }
+    }
+
+    pub(crate) fn set_detected_max_block_range(&self, max: u64) {
+        self.detected_max_block_range.store(max, Ordering::Relaxed);
+    }
+
+    /// The most logs wanted in a single `eth_getLogs` response, if limited by the config.
+    pub fn max_logs_per_response(&self) -> Option<usize> {
+        self.config.max_logs_per_response
+    }
+
+    /// Get the eth1 network id of the endpoint.
+    pub async fn get_network_id(&self) -> Result<NetworkId, Eth1Error> {
+        let response_body = self.send_rpc_request("net_version", json!([])).await?;
+        Ok(NetworkId::from_str(
+            response_result(&response_body)?
+                .ok_or("No result was returned for network id")?
+                .as_str()
+                .ok_or("Data was not string")?,
+        )?)
+    }
+
+    /// Returns a warning if the chain id and network id of the endpoint differ, or `None` if
+    /// they agree.
+    ///
+    /// A mismatch is not necessarily a problem, since some networks use different values, but it
+    /// is worth reporting when the endpoint is expected to be on a network where they match.
+    pub async fn network_id_mismatch(&self) -> Result<Option<String>, Eth1Error> {
+        let chain_id = u64::from(self.get_chain_id().await?);
+        let network_id = self.get_network_id().await?;
+        if chain_id == network_id.0 {
+            Ok(None)
+        } else {
+            Ok(Some(format!(
+                "Chain id {} differs from network id {}",
+                chain_id, network_id.0
+            )))
+        }
+    }
+
+    /// Get the eth1 chain id of the endpoint.
+    pub async fn get_chain_id(&self) -> Result<Eth1Id, Eth1Error> {
+        let response_body = self.send_rpc_request("eth_chainId", json!([])).await?;
+        Ok(decode::quantity(
+            &response_result(&response_body)?.ok_or("No result was returned for chain id")?,
+        )?
+        .into())
+    }
+
+    /// Returns the current block number.
+    ///
+    /// Falls back to reading the number of the latest block if the endpoint does not provide
+    /// `eth_blockNumber`.
+    pub async fn get_block_number(&self) -> Result<u64, Eth1Error> {
+        let response_body = match self.send_rpc_request("eth_blockNumber", json!([])).await {
+            Err(e) if e.is_method_not_found() => {
+                return Ok(self.get_block(BlockQuery::Latest).await?.number)
+            }
+            result => result?,
+        };
+        let head = decode::quantity(
+            &response_result(&response_body)?
+                .ok_or("No result field was returned for block number")?,
+        )
+        .map_err(|e| Eth1Error::InvalidResponse(format!("Failed to get block number: {}", e)))?;
+        if let (Some(cache), Some(settled)) =
+            (&self.cache, head.checked_sub(self.follow_distance()))
+        {
+            cache.settle(settled);
+        }
+        Ok(head)
+    }
+
+    /// Returns some fields from the block identified by `query`.
+    ///
+    /// Returns `Eth1Error::PendingBlock` if the block has not been mined yet.
+    pub async fn get_block(&self, query: BlockQuery) -> Result<Block, Eth1Error> {
+        let (method, block) = block_method_and_param(query);
+        let response_body = self.send_rpc_request(method, json!([block, false])).await?;
+        let response =
+            response_result(&response_body)?.ok_or("No result field was returned for block")?;
+
+        if Block::is_pending(&response) {
+            return Err(Eth1Error::PendingBlock);
+        }
+        let block = Block::from_value(&response)
+            .map_err(|e| Eth1Error::InvalidResponse(format!("Failed to get block: {}", e)))?;
+        if let (Some(cache), BlockQuery::Finalized) = (&self.cache, query) {
+            cache.settle(block.number);
+        }
+        Ok(block)
+    }
+
+    /// Returns the sync status of the endpoint.
+    pub async fn get_syncing(&self) -> Result<SyncStatus, Eth1Error> {
+        let response_body = self.send_rpc_request("eth_syncing", json!([])).await?;
+        let result =
+            response_result(&response_body)?.ok_or("No result field was returned for syncing")?;
+        match &result {
+            Value::Bool(false) => Ok(SyncStatus::Synced),
+            Value::Object(_) => {
+                let field = |name: &str| {
+                    decode::quantity(decode::required(&result, name)?)
+                        .map_err(|e| format!("Invalid {} in syncing response: {}", name, e))
+                };
+                Ok(SyncStatus::Syncing {
+                    starting_block: field("startingBlock")?,
+                    current_block: field("currentBlock")?,
+                    highest_block: field("highestBlock")?,
+                })
+            }
+            other => Err(format!("Unexpected syncing response: {}", other).into()),
+        }
+    }
+
+    /// Returns the value of the `get_deposit_root()` call at the given `address` and `block`.
+    ///
+    /// Returns `Ok(None)` if the deposit contract does not exist at `block`.
+    pub async fn get_deposit_root(
+        &self,
+        address: &str,
+        block: BlockQuery,
+    ) -> Result<Option<Hash256>, Eth1Error> {
+        let result = self.call(address, DEPOSIT_ROOT_FN_SIGNATURE, block).await?;
+        deposit_root_from_call(result)
+    }
+
+    /// Returns the value of the `get_deposit_count()` call at the given `address` and `block`.
+    ///
+    /// Returns `Ok(None)` if the deposit contract does not exist at `block`.
+    pub async fn get_deposit_count(
+        &self,
+        address: &str,
+        block: BlockQuery,
+    ) -> Result<Option<u64>, Eth1Error> {
+        let result = self
+            .call(address, DEPOSIT_COUNT_FN_SIGNATURE, block)
+            .await?;
+        deposit_count_from_call(result)
+    }
+
+    /// Returns the values of the `get_deposit_root()` and `get_deposit_count()` calls at the
+    /// given `address`, both read at the same `block`.
+    ///
+    /// A block tag (e.g., `BlockQuery::Latest`) is first resolved to a number, so the values are
+    /// consistent even if the head advances between the calls. The calls are sent as a single
+    /// JSON-RPC batch, falling back to separate requests if the endpoint does not support
+    /// batches.
+    ///
+    /// Returns an error if the deposit contract does not exist at `block`.
+    pub async fn get_root_and_count(
+        &self,
+        address: &str,
+        block: BlockQuery,
+    ) -> Result<(Hash256, u64), Eth1Error> {
+        let at = match block {
+            BlockQuery::Number(_) | BlockQuery::Hash(_) => block,
+            tag => BlockQuery::Number(self.get_block(tag).await?.number),
+        };
+
+        let params = vec![
+            call_params(address, DEPOSIT_ROOT_FN_SIGNATURE, at),
+            call_params(address, DEPOSIT_COUNT_FN_SIGNATURE, at),
+        ];
+        let (root, count) = match self.send_rpc_batch("eth_call", params).await {
+            Ok(results) => {
+                let mut results = results.into_iter();
+                let mut next = || -> Result<Option<Vec<u8>>, Eth1Error> {
+                    let result = results.next().ok_or("Batch response was too short")??;
+                    result
+                        .as_ref()
+                        .map(decode::bytes)
+                        .transpose()
+                        .map_err(Into::into)
+                };
+                let root = deposit_root_from_call(next()?)?;
+                let count = deposit_count_from_call(next()?)?;
+                (root, count)
+            }
+            Err(_) => (
+                self.get_deposit_root(address, at).await?,
+                self.get_deposit_count(address, at).await?,
+            ),
+        };
+
+        let not_deployed = || format!("No deposit contract at block {}", block_query_param(at));
+        Ok((
+            root.ok_or_else(not_deployed)?,
+            count.ok_or_else(not_deployed)?,
+        ))
+    }
+
+    /// As per `Self::get_root_and_count`, but at each of `blocks`, with every call sent in a
+    /// single JSON-RPC batch.
+    ///
+    /// Falls back to `Self::get_root_and_count` for each block if the endpoint does not support
+    /// batches. Returns an error if the deposit contract does not exist at any of `blocks`.
+    pub async fn get_roots_and_counts(
+        &self,
+        address: &str,
+        blocks: &[u64],
+    ) -> Result<Vec<(Hash256, u64)>, Eth1Error> {
+        if blocks.is_empty() {
+            return Ok(vec![]);
+        }
+        let params = blocks
+            .iter()
+            .flat_map(|block| {
+                let at = BlockQuery::Number(*block);
+                vec![
+                    call_params(address, DEPOSIT_ROOT_FN_SIGNATURE, at),
+                    call_params(address, DEPOSIT_COUNT_FN_SIGNATURE, at),
+                ]
+            })
+            .collect();
+
+        match self.send_rpc_batch("eth_call", params).await {
+            Ok(results) => {
+                let mut results = results.into_iter();
+                let mut next = || -> Result<Option<Vec<u8>>, Eth1Error> {
+                    let result = results.next().ok_or("Batch response was too short")??;
+                    result
+                        .as_ref()
+                        .map(decode::bytes)
+                        .transpose()
+                        .map_err(Into::into)
+                };
+                blocks
+                    .iter()
+                    .map(|block| {
+                        let not_deployed = || format!("No deposit contract at block {}", block);
+                        let root = deposit_root_from_call(next()?)?.ok_or_else(not_deployed)?;
+                        let count = deposit_count_from_call(next()?)?.ok_or_else(not_deployed)?;
+                        Ok((root, count))
+                    })
+                    .collect()
+            }
+            Err(_) => {
+                let mut values = Vec::with_capacity(blocks.len());
+                for block in blocks {
+                    values.push(
+                        self.get_root_and_count(address, BlockQuery::Number(*block))
+                            .await?,
+                    );
+                }
+                Ok(values)
+            }
+        }
+    }
+
+    /// Performs an `eth_call` to `address` with the given `hex_data` at `block`, returning the
+    /// bytes of the result.
+    pub async fn call(
+        &self,
+        address: &str,
+        hex_data: &str,
+        block: BlockQuery,
+    ) -> Result<Option<Vec<u8>>, Eth1Error> {
+        let response_body = self
+            .send_rpc_request("eth_call", call_params(address, hex_data, block))
+            .await?;
+        match response_result(&response_body)? {
+            None => Ok(None),
+            Some(result) => Ok(Some(decode::bytes(&result)?)),
+        }
+    }
+
+    /// Returns logs for the `Self::deposit_topic`, for the given `address` in the given
+    /// `block_height_range`.
+    ///
+    /// The range is half-open, like any `Range`: the endpoint treats `toBlock` as inclusive, so
+    /// it is sent as `end - 1`. An empty range returns no logs without contacting the endpoint.
+    ///
+    /// Returns an error without contacting the endpoint if the range is inverted (`start > end`)
+    /// or wider than the configured `max_block_range`. Returns an error if any log does not have
+    /// the deposit topic, since some proxies ignore the filter and return unrelated
+    /// events, which must not be counted as deposits.
+    pub async fn get_deposit_logs_in_range(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<Vec<Log>, Eth1Error> {
+        self.get_deposit_logs_for_addresses(&[address], block_height_range)
+            .await
+    }
+
+    /// As per `Self::get_deposit_logs_in_range`, but for the logs of each of `addresses` in a
+    /// single request, e.g., a deposit contract and its proxy.
+    ///
+    /// The logs of all addresses are returned together in log order; the address which
+    /// emitted each log is recorded in it.
+    pub async fn get_deposit_logs_for_addresses(
+        &self,
+        addresses: &[&str],
+        block_height_range: Range<u64>,
+    ) -> Result<Vec<Log>, Eth1Error> {
+        if block_height_range.start > block_height_range.end {
+            return Err(Eth1Error::InvalidRequest(format!(
+                "Block range {}..{} is inverted",
+                block_height_range.start, block_height_range.end
+            )));
+        }
+        if block_height_range.start == block_height_range.end {
+            return Ok(vec![]);
+        }
+        if let Some(max) = self.config.max_block_range {
+            let width = block_height_range
+                .end
+                .saturating_sub(block_height_range.start);
+            if width > max {
+                return Err(Eth1Error::InvalidRequest(format!(
+                    "Block range {}..{} spans {} blocks, which exceeds the maximum of {}. \
+                     Request logs in smaller chunks.",
+                    block_height_range.start, block_height_range.end, width, max
+                )));
+            }
+        }
+
+        let params = json! ([{
+            "address": addresses,
+            "topics": [self.deposit_topic()],
+            "fromBlock": format!("0x{:x}", block_height_range.start),
+            "toBlock": format!("0x{:x}", block_height_range.end - 1),
+        }]);
+
+        let blocks = block_height_range.end - block_height_range.start;
+        let mut reservation = match &self.in_flight {
+            Some(in_flight) => Some(in_flight.reserve(blocks).await),
+            None => None,
+        };
+        let (logs, response_bytes) = self.send_logs_request(params).await?;
+        if let Some(reservation) = &mut reservation {
+            reservation.record(response_bytes, blocks);
+        }
+        for log in &logs {
+            check_deposit_topic(log, self.deposit_topic()).map_err(|e| {
+                Eth1Error::InvalidResponse(format!(
+                    "Endpoint returned a log in block {} which was not filtered: {}",
+                    log.block_number, e
+                ))
+            })?;
+        }
+        Ok(logs)
+    }
+
+    /// As per `Self::get_deposit_logs_in_range`, but decodes each log and returns the deposits
+    /// alongside the `block_height_range` they were requested for.
+    ///
+    /// Useful for matching up the results of requests which complete out of order.
+    pub async fn get_deposits_in_range(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<(Range<u64>, Vec<DepositLog>), Eth1Error> {
+        let deposits = self
+            .get_deposit_logs_in_range(address, block_height_range.clone())
+            .await?
+            .iter()
+            .map(|log| decode_deposit_log_with_topic(log, self.deposit_topic()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((block_height_range, deposits))
+    }
+
+    /// Sends an RPC request to the endpoint, using a POST with the given `method` and `params`.
+    ///
+    /// Tries to receive the response and parse the body as a `String`.
+    pub async fn send_rpc_request(&self, method: &str, params: Value) -> Result<String, Eth1Error> {
+        self.send_rpc_request_bytes(method, params)
+            .await
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// As per `Self::send_rpc_request`, but returns the raw bytes of the body.
+    ///
+    /// Requests which fail with a transient error are retried according to the `RetryConfig`.
+    /// Each attempt is bounded by `Self::timeout_for` the `method`, and is dropped (cancelling
+    /// the request) if it takes longer. Attempts are also cut short by the `overall_deadline`.
+    ///
+    /// Responses are served from, and stored in, the cache if it is enabled and the request
+    /// is for a block hash, or a numbered block which is settled: the follow distance behind the
+    /// head, or finalized. The head is fetched again if the block is newer than the settled
+    /// block last seen.
+    pub(crate) async fn send_rpc_request_bytes(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Vec<u8>, Eth1Error> {
+        let cache_entry = self
+            .cache
+            .as_ref()
+            .and_then(|cache| Some((cache, ResponseCache::key(method, &params)?)));
+        if let Some((cache, key)) = &cache_entry {
+            if let Some(body) = cache.get(&key.key) {
+                return Ok(body);
+            }
+        }
+
+        let body = self.encode_body(&self.request_body(method, params));
+        let response_body = self.send_with_retries(method, body).await?;
+        if let Some((cache, key)) = cache_entry {
+            if !cache.is_settled(key.block) {
+                // The head may have advanced far enough since it was last seen.
+                self.settle_cache_to_head(cache).await;
+            }
+            if cache.is_settled(key.block) {
+                cache.insert(key.key, response_body.clone());
+            }
+        }
+        Ok(response_body)
+    }
+
+    /// Records in the `cache` that the blocks up to the follow distance behind the head are
+    /// settled, or leaves it as it is if the head cannot be fetched.
+    ///
+    /// The head is requested directly rather than through `Self::get_block_number`, which would
+    /// recurse into the cache.
+    async fn settle_cache_to_head(&self, cache: &ResponseCache) {
+        let method = "eth_blockNumber";
+        let body = self.encode_body(&self.request_body(method, json!([])));
+        let head = self
+            .send_with_retries(method, body)
+            .await
+            .ok()
+            .and_then(|body| response_result(&String::from_utf8_lossy(&body)).ok()?)
+            .and_then(|result| decode::quantity(&result).ok());
+        if let Some(settled) = head.and_then(|head| head.checked_sub(self.follow_distance())) {
+            cache.settle(settled);
+        }
+    }
+
+    /// Sends an `eth_getLogs` request with `params`, returning the logs and the size of the
+    /// response body.
+    ///
+    /// The logs are decoded as the body arrives (see `read_logs_response`), unless the body is
+    /// needed whole: to be cached, or to be decoded leniently as per `lenient_results`.
+    async fn send_logs_request(&self, params: Value) -> Result<(Vec<Log>, usize), Eth1Error> {
+        let method = "eth_getLogs";
+        if self.cache.is_some() || self.config.lenient_results {
+            let response_body = self.send_rpc_request_bytes(method, params).await?;
+            let logs = self.decode_logs(&response_body).map_err(|e| {
+                Eth1Error::InvalidResponse(format!("Failed to get logs in range: {}", e))
+            })?;
+            return Ok((logs, response_body.len()));
+        }
+
+        let body = self.encode_body(&self.request_body(method, params));
+        self.check_request_size(method, &body)?;
+        let _request = self.begin_request()?;
+        self.with_retries(method, || self.post_logs(method, body.clone()))
+            .await
+    }
+
+    /// Returns the number of responses in the cache, or `None` if it is disabled.
+    pub fn cached_responses(&self) -> Option<usize> {
+        self.cache.as_ref().map(|cache| cache.len())
+    }
+
+    /// Shuts down the client and its clones: new requests fail, the requests in flight are
+    /// waited for and the response cache is emptied.
+    ///
+    /// Call before exiting, e.g., on Ctrl-C, after saving any progress. Requests which are
+    /// dropped rather than awaited (e.g., by a `select!`) count as finished.
+    pub async fn shutdown(&self) {
+        self.lifecycle.shut_down.store(true, Ordering::SeqCst);
+        while self.lifecycle.active.load(Ordering::SeqCst) > 0 {
+            // The timeout guards against a wake-up missed between the load and the wait.
+            let _ = timeout(Duration::from_millis(100), self.lifecycle.idle.notified()).await;
+        }
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns `true` if `Self::shutdown` has been called on this client or a clone.
+    pub fn is_shut_down(&self) -> bool {
+        self.lifecycle.shut_down.load(Ordering::SeqCst)
+    }
+
+    /// Counts a request as in flight until the returned guard is dropped, or returns an error if
+    /// the client has been shut down.
+    fn begin_request(&self) -> Result<ActiveRequest<'_>, Eth1Error> {
+        self.lifecycle.active.fetch_add(1, Ordering::SeqCst);
+        let request = ActiveRequest(&self.lifecycle);
+        if self.is_shut_down() {
+            return Err(Eth1Error::InvalidRequest(
+                "The client has been shut down".to_string(),
+            ));
+        }
+        Ok(request)
+    }
+
+    /// Sends a JSON-RPC batch of `method` requests, one for each of `params`, in a single POST.
+    ///
+    /// Returns the result of each request in the order of `params`. An error is returned for the
+    /// whole batch if the request fails or the endpoint does not support batches, and for an
+    /// individual request if its response contains an error object.
+    pub async fn send_rpc_batch(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<Result<Option<Value>, Eth1Error>>, Eth1Error> {
+        let count = params.len();
+        let body = params
+            .into_iter()
+            .enumerate()
+            .map(|(id, params)| {
+                let mut request = self.request_body(method, params);
+                request["id"] = json!(id);
+                request
+            })
+            .collect::<Vec<_>>();
+
+        let response_body = self
+            .send_with_retries(method, self.encode_body(&Value::Array(body)))
+            .await?;
+        let responses = match serde_json::from_slice::<Value>(&response_body)
+            .map_err(|e| format!("Failed to parse batch response: {:?}", e))?
+        {
+            Value::Array(responses) => responses,
+            other => {
+                return Err(format!("Batch response was not an array: {}", other).into());
+            }
+        };
+
+        let mut results = (0..count)
+            .map(|id| Err(format!("No response to batch request {}", id).into()))
+            .collect::<Vec<_>>();
+        for response in responses {
+            let id = response
+                .get("id")
+                .and_then(Value::as_u64)
+                .map(|id| id as usize)
+                .filter(|id| *id < count)
+                .ok_or_else(|| format!("Batch response had an unknown id: {}", response))?;
+            results[id] = match response.get("error") {
+                Some(error) => Err(Eth1Error::from_rpc_error(error)),
+                None => Ok(response.get("result").cloned()),
+            };
+        }
+        Ok(results)
+    }
+
+    /// Makes a single attempt at a `method` request, without any retries.
+    #[cfg(feature = "tower")]
+    pub(crate) async fn send_rpc_request_once(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Vec<u8>, Eth1Error> {
+        let body = self.encode_body(&self.request_body(method, params));
+        self.check_request_size(method, &body)?;
+        let _request = self.begin_request()?;
+        self.post(method, body).await
+    }
+
+    /// POSTs `body` to the endpoint, retrying transient errors as per the `RetryConfig`.
+    async fn send_with_retries(&self, method: &str, body: String) -> Result<Vec<u8>, Eth1Error> {
+        self.check_request_size(method, &body)?;
+        let _request = self.begin_request()?;
+        self.with_retries(method, || self.post(method, body.clone()))
+            .await
+    }
+
+    /// Makes attempts at a `method` request with `make_attempt` until one succeeds, one fails
+    /// with an error which should not be retried, or a limit of the `RetryConfig` is reached.
+    ///
+    /// Each attempt is bounded by `Self::timeout_for` the `method` and the `overall_deadline`,
+    /// and fails with `Eth1Error::Timeout` (dropping it) if it takes longer.
+    async fn with_retries<F, Fut, T>(
+        &self,
+        method: &str,
+        mut make_attempt: F,
+    ) -> Result<T, Eth1Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, Eth1Error>>,
+    {
+        let retry = &self.config.retry;
+        let deadline = retry
+            .overall_deadline
+            .map(|deadline| Instant::now() + deadline);
+        let remaining =
+            || deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+        let mut attempt = 0;
+        loop {
+            let attempt_timeout = match remaining() {
+                Some(remaining) => std::cmp::min(remaining, self.timeout_for(method)),
+                None => self.timeout_for(method),
+            };
+            let attempt_result = timeout(attempt_timeout, make_attempt())
+                .await
+                .unwrap_or(Err(Eth1Error::Timeout));
+            match attempt_result {
+                Err(e) if attempt < retry.max_retries && retry.should_retry(&e, attempt) => {
+                    let backoff = retry.backoff(attempt);
+                    if remaining().is_some_and(|remaining| remaining <= backoff)
+                        || !self.take_retry()
+                    {
+                        return Err(e);
+                    }
+                    delay_for(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Decodes an `eth_getLogs` (or filter) response, leniently if `lenient_results` is set.
+    pub(crate) fn decode_logs(&self, body: &[u8]) -> Result<Vec<Log>, String> {
+        if self.config.lenient_results {
+            decode_logs_response_lenient(body)
+        } else {
+            decode_logs_response(body)
+        }
+    }
+
+    /// Returns an error if `body` is larger than the configured `max_request_bytes`.
+    fn check_request_size(&self, method: &str, body: &str) -> Result<(), Eth1Error> {
+        match self.config.max_request_bytes {
+            Some(max) if body.len() > max => Err(Eth1Error::InvalidRequest(format!(
+                "Request body for {} is {} bytes, which exceeds the maximum of {}",
+                method,
+                body.len(),
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a retry against the `retry_budget`, returning `false` if it is used up.
+    fn take_retry(&self) -> bool {
+        let budget = self.config.retry.retry_budget.unwrap_or(usize::MAX);
+        self.retries
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                if used < budget {
+                    Some(used + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Returns the number of retries made by this client and its clones.
+    pub fn retries_used(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the `retry_budget` has been used up, so no more requests will be
+    /// retried.
+    pub fn retry_budget_exhausted(&self) -> bool {
+        self.config
+            .retry
+            .retry_budget
+            .is_some_and(|budget| self.retries_used() >= budget)
+    }
+
+    /// Returns the JSON-RPC request object for `method`, with the configured `jsonrpc_version`.
+    fn request_body(&self, method: &str, params: Value) -> Value {
+        let mut body = json! ({
+            "method": method,
+            "params": params,
+            "id": 1
+        });
+        if let Some(version) = &self.config.jsonrpc_version {
+            body["jsonrpc"] = json!(version);
+        }
+        body
+    }
+
+    /// Serializes a request `body`, compact or pretty-printed as per `pretty_json`.
+    fn encode_body(&self, body: &Value) -> String {
+        if self.config.pretty_json {
+            serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string())
+        } else {
+            body.to_string()
+        }
+    }
+
+    /// Makes a single attempt at POSTing `body` to the endpoint.
+    ///
+    /// Returns an error if the response contains a JSON-RPC error object.
+    async fn post(&self, method: &str, body: String) -> Result<Vec<u8>, Eth1Error> {
+        let mut response = self.send_post(method, body).await?;
+
+        // Read the body chunk-by-chunk so that an oversized response can be abandoned before it
+        // has been buffered in its entirety.
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(Eth1Error::from_reqwest)? {
+            bytes.extend_from_slice(&chunk);
+            self.check_response_size(bytes.len() as u64)?;
+        }
+
+        // Only the `error` field is deserialized, the rest of the body is skipped over. The
+        // response to a batch is an array, whose errors are left to the caller.
+        #[derive(Deserialize)]
+        struct ErrorField {
+            error: Option<Value>,
+        }
+        let is_object = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+        if is_object {
+            if let Ok(ErrorField { error: Some(error) }) = serde_json::from_slice(&bytes) {
+                return Err(Eth1Error::from_rpc_error(&error));
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// As per `Self::post`, but decodes the body as an `eth_getLogs` response while it arrives,
+    /// on a blocking thread, returning the logs and the size of the body.
+    ///
+    /// At most `BODY_CHUNKS_IN_FLIGHT` chunks of the body are held at once, rather than the
+    /// whole body. Returns an error if the response contains a JSON-RPC error object.
+    async fn post_logs(&self, method: &str, body: String) -> Result<(Vec<Log>, usize), Eth1Error> {
+        let mut response = self.send_post(method, body).await?;
+
+        let (mut chunks, receiver) = mpsc::channel(BODY_CHUNKS_IN_FLIGHT);
+        let decoder = spawn_blocking(move || read_logs_response(ChunkReader::new(receiver)));
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await.map_err(Eth1Error::from_reqwest)? {
+            size += chunk.len();
+            self.check_response_size(size as u64)?;
+            if chunks.send(chunk).await.is_err() {
+                // The decoder has stopped early, on a body which is not a valid response.
+                break;
+            }
+        }
+        // Ends the body, so that the decoder finishes.
+        drop(chunks);
+
+        let logs = decoder
+            .await
+            .map_err(|e| format!("Failed to decode logs: {}", e))?
+            .map_err(|e| format!("Failed to get logs in range: {}", e))?;
+        logs.map(|logs| (logs, size))
+            .map_err(|error| Eth1Error::from_rpc_error(&error))
+    }
+
+    /// Returns an error if a response body of `size` bytes exceeds the configured
+    /// `max_response_bytes`.
+    fn check_response_size(&self, size: u64) -> Result<(), Eth1Error> {
+        match self.config.max_response_bytes {
+            Some(limit) if size > limit as u64 => Err(Eth1Error::ResponseTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// POSTs `body` to the endpoint, returning the response once its headers have been received
+    /// and checked, before its body is read.
+    async fn send_post(&self, method: &str, body: String) -> Result<Response, Eth1Error> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .header(CONTENT_TYPE, "application/json")
+            .timeout(self.timeout_for(method))
+            .body(body)
+            .send()
+            .await
+            .map_err(Eth1Error::from_reqwest)?;
+        if response.status() != StatusCode::OK {
+            return Err(Eth1Error::HttpStatus(response.status().as_u16()));
+        };
+        let encoding = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .ok_or("No content-type header in response")?
+            .to_str()
+            .map(|s| s.to_string())
+            .map_err(|e| format!("Failed to parse content-type header: {}", e))?;
+        match encoding.as_str() {
+            "application/json" => (),
+            "application/json; charset=utf-8" => (),
+            other => return Err(format!("Unsupported encoding: {}", other).into()),
+        }
+        // An oversized response can be abandoned before any of its body is read.
+        if let Some(length) = response.content_length() {
+            self.check_response_size(length)?;
+        }
+
+        Ok(response)
+    }
+}
+
+/// Get the eth1 network id of the given endpoint.
+pub async fn get_network_id(endpoint: &str, timeout: Duration) -> Result<NetworkId, Eth1Error> {
+    Eth1Client::new(endpoint, timeout).get_network_id().await
+}
+
+/// Get the eth1 chain id of the given endpoint.
+pub async fn get_chain_id(endpoint: &str, timeout: Duration) -> Result<Eth1Id, Eth1Error> {
+    Eth1Client::new(endpoint, timeout).get_chain_id().await
+}
+
+/// Returns the current block number.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_block_number(endpoint: &str, timeout: Duration) -> Result<u64, Eth1Error> {
+    Eth1Client::new(endpoint, timeout).get_block_number().await
+}
+
+/// Returns some fields from the block identified by `query`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_block(
+    endpoint: &str,
+    query: BlockQuery,
+    timeout: Duration,
+) -> Result<Block, Eth1Error> {
+    Eth1Client::new(endpoint, timeout).get_block(query).await
+}
+
+/// Returns the sync status of the given endpoint.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_syncing(endpoint: &str, timeout: Duration) -> Result<SyncStatus, Eth1Error> {
+    Eth1Client::new(endpoint, timeout).get_syncing().await
+}
+
+/// Returns the value of the `get_deposit_root()` call at the given `address` and `block`.
+///
+/// Returns `Ok(None)` if the deposit contract does not exist at `block`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_root(
+    endpoint: &str,
+    address: &str,
+    block: BlockQuery,
+    timeout: Duration,
+) -> Result<Option<Hash256>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_root(address, block)
+        .await
+}
+
+/// Returns the value of the `get_deposit_count()` call at the given `address` and `block`.
+///
+/// Returns `Ok(None)` if the deposit contract does not exist at `block`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_count(
+    endpoint: &str,
+    address: &str,
+    block: BlockQuery,
+    timeout: Duration,
+) -> Result<Option<u64>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_count(address, block)
+        .await
+}
+
+/// Returns the values of the `get_deposit_root()` and `get_deposit_count()` calls at the given
+/// `address`, both read at the same `block`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_root_and_count(
+    endpoint: &str,
+    address: &str,
+    block: BlockQuery,
+    timeout: Duration,
+) -> Result<(Hash256, u64), Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_root_and_count(address, block)
+        .await
+}
+
+/// Returns logs for the `DEPOSIT_EVENT_TOPIC`, for the given `address` in the given
+/// `block_height_range`.
+///
+/// The range is half-open, i.e., `block_height_range.end` is not included.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_logs_in_range(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<Vec<Log>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_logs_in_range(address, block_height_range)
+        .await
+}
+
+/// Returns logs for the `DEPOSIT_EVENT_TOPIC`, for each of `addresses` in the given
+/// `block_height_range`, using a single request.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_logs_for_addresses(
+    endpoint: &str,
+    addresses: &[&str],
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<Vec<Log>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_logs_for_addresses(addresses, block_height_range)
+        .await
+}
+
+/// Returns the decoded deposits for the given `address` in the given `block_height_range`,
+/// alongside the range itself.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposits_in_range(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<(Range<u64>, Vec<DepositLog>), Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposits_in_range(address, block_height_range)
+        .await
+}
+
+/// Sends an RPC request to `endpoint`, using a POST with the given `body`.
+///
+/// Tries to receive the response and parse the body as a `String`.
+///
+/// Note: this creates a new HTTP client for each request. Use an `Eth1Client` to reuse one
+/// across requests.
+pub async fn send_rpc_request(
+    endpoint: &str,
+    method: &str,
+    params: Value,
+    timeout: Duration,
+) -> Result<String, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .send_rpc_request(method, params)
+        .await
+}
+
+/// The requests in flight through an `Eth1Client` and its clones.
+#[derive(Default)]
+struct Lifecycle {
+    shut_down: AtomicBool,
+    active: AtomicUsize,
+    /// Notified when the last request in flight finishes.
+    idle: Notify,
+}
+
+/// A request counted in `Lifecycle::active` until dropped.
+struct ActiveRequest<'a>(&'a Lifecycle);
+
+impl Drop for ActiveRequest<'_> {
+    fn drop(&mut self) {
+        if self.0.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify();
+        }
+    }
+}
+
+/// Accepts an entire HTTP body (as a string) and returns the `result` field, as a serde `Value`.
+pub(crate) fn response_result(response: &str) -> Result<Option<Value>, Eth1Error> {
+    let json = serde_json::from_str::<Value>(response)
+        .map_err(|e| format!("Failed to parse response: {:?}", e))?;
+
+    if let Some(error) = json.get("error") {
+        Err(Eth1Error::from_rpc_error(error))
+    } else {
+        Ok(json.get("result").cloned())
+    }
+}
+
+/// Decodes the result of a `get_deposit_root()` call, see `decode_deposit_root`.
+fn deposit_root_from_call(result: Option<Vec<u8>>) -> Result<Option<Hash256>, Eth1Error> {
+    match result {
+        None => Err("Deposit root response was none".into()),
+        Some(bytes) => Ok(decode_deposit_root(&bytes)?),
+    }
+}
+
+/// Decodes the result of a `get_deposit_count()` call, see `decode_deposit_count`.
+fn deposit_count_from_call(result: Option<Vec<u8>>) -> Result<Option<u64>, Eth1Error> {
+    match result {
+        None => Err("Deposit count response was none".into()),
+        Some(bytes) => Ok(decode_deposit_count(&bytes)?),
+    }
+}
+
+/// Returns the `eth_call` params for a call to `address` with `hex_data` at `block`.
+pub(crate) fn call_params(address: &str, hex_data: &str, block: BlockQuery) -> Value {
+    json! ([
+        {
+            "to": address,
+            "data": hex_data,
+        },
+        block_query_param(block)
+    ])
+}
+
+/// Returns the JSON-RPC block parameter for `block`, as taken by state methods such as
+/// `eth_call` and `eth_getCode`.
+///
+/// A number or tag is a string, whilst a hash is an EIP-1898 `{"blockHash": ..}` object.
+/// Methods with other shapes use `block_method_and_param` or `block_filter_param`.
+pub(crate) fn block_query_param(block: BlockQuery) -> Value {
+    match block {
+        BlockQuery::Number(number) => json!(format!("0x{:x}", number)),
+        BlockQuery::Hash(hash) => json!({ "blockHash": hash }),
+        BlockQuery::Latest => json!("latest"),
+        BlockQuery::Safe => json!("safe"),
+        BlockQuery::Finalized => json!("finalized"),
+    }
+}
+
+/// Returns the method for fetching the block object of `block` and its block parameter, i.e.,
+/// `eth_getBlockByHash` for a hash and `eth_getBlockByNumber` otherwise.
+pub(crate) fn block_method_and_param(block: BlockQuery) -> (&'static str, Value) {
+    match block {
+        BlockQuery::Hash(hash) => ("eth_getBlockByHash", json!(hash)),
+        other => ("eth_getBlockByNumber", block_query_param(other)),
+    }
+}
+
+/// Returns the `fromBlock` field of a log filter which starts at `block`.
+///
+/// Returns an error for a hash, since a filter can only start at a block number or tag.
+pub(crate) fn block_filter_param(block: BlockQuery) -> Result<Value, Eth1Error> {
+    match block {
+        BlockQuery::Hash(hash) => Err(Eth1Error::InvalidRequest(format!(
+            "A log filter cannot start at block hash {:?}, use a block number",
+            hash
+        ))),
+        other => Ok(block_query_param(other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::INTERNAL_ERROR_CODE;
+    use std::cell::Cell;
+
+    /// Returns a client which retries twice, after a millisecond.
+    fn retrying_client() -> Eth1Client {
+        let mut config = ClientConfig::default();
+        config.retry.max_retries = 2;
+        config.retry.initial_backoff = Duration::from_millis(1);
+        Eth1Client::with_config("http://localhost:8545", config)
+    }
+
+    /// Returns the result of `client` making a request whose every attempt fails with an RPC
+    /// error `code`, along with the number of attempts made.
+    async fn attempts_with_rpc_error(
+        client: &Eth1Client,
+        code: i64,
+    ) -> (Result<Vec<u8>, Eth1Error>, usize) {
+        let attempts = Cell::new(0);
+        let result = client
+            .with_retries("eth_getLogs", || {
+                attempts.set(attempts.get() + 1);
+                async move {
+                    Err(Eth1Error::Rpc {
+                        code,
+                        message: "error".to_string(),
+                    })
+                }
+            })
+            .await;
+        (result, attempts.get())
+    }
+
+    #[tokio::test]
+    async fn internal_error_is_retried() {
+        let client = retrying_client();
+        let (result, attempts) = attempts_with_rpc_error(&client, INTERNAL_ERROR_CODE).await;
+        assert!(matches!(result, Err(Eth1Error::Rpc { code, .. }) if code == INTERNAL_ERROR_CODE));
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn invalid_params_error_is_not_retried() {
+        let client = retrying_client();
+        let (result, attempts) = attempts_with_rpc_error(&client, -32602).await;
+        assert!(matches!(result, Err(Eth1Error::Rpc { code: -32602, .. })));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn attempt_which_never_responds_times_out() {
+        let client = Eth1Client::new("http://localhost:8545", Duration::from_millis(1));
+        let result: Result<Vec<u8>, _> = client
+            .with_retries("eth_blockNumber", futures::future::pending)
+            .await;
+        assert!(matches!(result, Err(Eth1Error::Timeout)));
+    }
+}
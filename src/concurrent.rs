@@ -0,0 +1,137 @@
+//! Fetching the deposit logs of a wide block range with several requests in flight at once.
+
+use crate::deposit_log::sort_and_dedup_deposits;
+use crate::{chunk_range, DepositLog, Eth1Client, Eth1Error};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::ops::Range;
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Returns all deposits to `address` in `block_height_range`, ordered by deposit index.
+    ///
+    /// The range is requested in chunks of `Self::log_chunk_size` blocks, with up to
+    /// `concurrency` requests in flight at once. Responses arrive out of order, so they are
+    /// sorted by block range and deduplicated by deposit index before returning. The result is
+    /// identical to fetching each chunk sequentially.
+    pub async fn get_deposits_concurrently(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+        concurrency: usize,
+    ) -> Result<Vec<DepositLog>, Eth1Error> {
+        let chunks = chunk_range(block_height_range, self.log_chunk_size());
+
+        let responses: Vec<(Range<u64>, Vec<DepositLog>)> = stream::iter(chunks)
+            .map(|chunk| self.get_deposits_in_range(address, chunk))
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(merge_chunks(responses))
+    }
+
+    /// Returns the deposits to `address` in each chunk of `block_height_range`, ordered by block
+    /// range, with up to `concurrency` requests in flight at once.
+    ///
+    /// Unlike `Self::get_deposits_concurrently`, an error does not end the fetch: every chunk is
+    /// requested and returned with its own result, so the deposits of the chunks which succeeded
+    /// are kept and only the failed chunks need to be fetched again.
+    pub async fn get_deposit_chunks_concurrently(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+        concurrency: usize,
+    ) -> Vec<(Range<u64>, Result<Vec<DepositLog>, Eth1Error>)> {
+        let chunks = chunk_range(block_height_range, self.log_chunk_size());
+
+        let mut responses: Vec<_> = stream::iter(chunks)
+            .map(|chunk| async move {
+                let result = self.get_deposits_in_range(address, chunk.clone()).await;
+                (chunk, result.map(|(_, deposits)| deposits))
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+        responses.sort_by_key(|(range, _)| range.start);
+
+        responses
+    }
+}
+
+/// Returns the deposits of chunk `responses` received in any order, ordered by block range and
+/// then deduplicated by deposit index.
+fn merge_chunks(mut responses: Vec<(Range<u64>, Vec<DepositLog>)>) -> Vec<DepositLog> {
+    responses.sort_by_key(|(range, _)| range.start);
+
+    let mut deposits: Vec<DepositLog> = responses
+        .into_iter()
+        .flat_map(|(_, deposits)| deposits)
+        .collect();
+    sort_and_dedup_deposits(&mut deposits);
+    deposits
+}
+
+/// Returns all deposits to `address` in `block_height_range`, ordered by deposit index, with up
+/// to `concurrency` requests in flight at once.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposits_concurrently(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    concurrency: usize,
+    timeout: Duration,
+) -> Result<Vec<DepositLog>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposits_concurrently(address, block_height_range, concurrency)
+        .await
+}
+
+/// Returns the deposits to `address` in each chunk of `block_height_range`, ordered by block
+/// range, with the result of each chunk rather than stopping at the first error.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposit_chunks_concurrently(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    concurrency: usize,
+    timeout: Duration,
+) -> Vec<(Range<u64>, Result<Vec<DepositLog>, Eth1Error>)> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposit_chunks_concurrently(address, block_height_range, concurrency)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deposit;
+
+    #[test]
+    fn merged_chunks_match_a_sequential_fetch() {
+        // The last deposit of the third chunk is returned again by the fourth, as by a node
+        // which returns the same log twice.
+        let chunks = vec![
+            (0..10, vec![deposit(0, 3), deposit(1, 7)]),
+            (10..20, vec![]),
+            (20..30, vec![deposit(2, 21), deposit(3, 21), deposit(4, 29)]),
+            (30..40, vec![deposit(4, 29), deposit(5, 35)]),
+        ];
+        let mut sequential: Vec<DepositLog> = chunks
+            .iter()
+            .flat_map(|(_, deposits)| deposits.clone())
+            .collect();
+        sort_and_dedup_deposits(&mut sequential);
+        assert_eq!(
+            sequential.iter().map(|d| d.index).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+
+        let mut arrival = chunks.clone();
+        arrival.reverse();
+        arrival.swap(0, 2);
+        assert_eq!(merge_chunks(arrival), sequential);
+        assert_eq!(merge_chunks(chunks), sequential);
+    }
+}
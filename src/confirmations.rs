@@ -0,0 +1,47 @@
+//! Fetching only the deposits which are deep enough below the head to be unlikely to re-org.
+
+use crate::{DepositLog, Eth1Client, Eth1Error};
+use std::ops::Range;
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Returns the deposits to `address` in `block_height_range` which have at least
+    /// `min_confirmations`, ordered by deposit index.
+    ///
+    /// A deposit in the head block has one confirmation, so `min_confirmations` of `0` or `1`
+    /// returns every deposit up to the head. The range is clipped to the confirmed blocks before
+    /// it is requested, so no logs are fetched for blocks which are too recent.
+    pub async fn get_confirmed_deposits(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+        min_confirmations: u64,
+    ) -> Result<Vec<DepositLog>, Eth1Error> {
+        let head = self.get_block_number().await?;
+        // One past the last block with `head - block + 1 >= min_confirmations`.
+        let confirmed_end = (head + 1).saturating_sub(min_confirmations.saturating_sub(1));
+        let end = std::cmp::min(block_height_range.end, confirmed_end);
+        if block_height_range.start >= end {
+            return Ok(vec![]);
+        }
+
+        self.get_deposits_concurrently(address, block_height_range.start..end, 1)
+            .await
+    }
+}
+
+/// Returns the deposits to `address` in `block_height_range` which have at least
+/// `min_confirmations`, ordered by deposit index.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_confirmed_deposits(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    min_confirmations: u64,
+    timeout: Duration,
+) -> Result<Vec<DepositLog>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_confirmed_deposits(address, block_height_range, min_confirmations)
+        .await
+}
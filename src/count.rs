@@ -0,0 +1,387 @@
+//! Counting deposits using the deposit contract's `get_deposit_count()` function.
+
+use crate::client::call_params;
+use crate::deposit_log::sort_and_dedup_deposits;
+use crate::{
+    chunk_range, decode, decode_deposit_count, BlockQuery, DepositLog, Eth1Client, Eth1Error,
+    DEPOSIT_COUNT_FN_SIGNATURE,
+};
+use std::ops::Range;
+use std::time::Duration;
+
+/// The number of deposits made in a block range, counted both from the logs and from the
+/// change in the contract's `get_deposit_count()`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RangeDepositCount {
+    /// The number of distinct deposits in the logs of the range.
+    pub in_logs: u64,
+    /// The deposit count at the end of the range, less the count before its start.
+    pub from_count: u64,
+    /// `true` if `in_logs == from_count`.
+    pub matched: bool,
+}
+
+impl Eth1Client {
+    /// Returns the number of deposits made to `address` in blocks `start..=end` (inclusive), using
+    /// two `get_deposit_count()` calls.
+    ///
+    /// This is much cheaper than fetching and counting the logs for the range. The count is
+    /// treated as zero at blocks before the contract was deployed.
+    pub async fn count_deposits_between(
+        &self,
+        address: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<u64, Eth1Error> {
+        if start > end {
+            return Err(Eth1Error::InvalidRequest(format!(
+                "Start block {} is after end block {}",
+                start, end
+            )));
+        }
+
+        let count_at_end = self
+            .get_deposit_count(address, BlockQuery::Number(end))
+            .await?
+            .unwrap_or(0);
+        let count_before_start = match start.checked_sub(1) {
+            Some(before) => self
+                .get_deposit_count(address, BlockQuery::Number(before))
+                .await?
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        count_at_end.checked_sub(count_before_start).ok_or_else(|| {
+            Eth1Error::InvalidResponse(format!(
+                "Deposit count decreased from {} at block {} to {} at block {}",
+                count_before_start,
+                start.saturating_sub(1),
+                count_at_end,
+                end
+            ))
+        })
+    }
+
+    /// Counts the deposits made to `address` in `block_height_range` from both its logs and
+    /// the `get_deposit_count()` at either end of it, see `RangeDepositCount`.
+    ///
+    /// A mismatch means that the endpoint either omitted logs (e.g., because they were pruned)
+    /// or returned an inconsistent contract state, so neither source can be trusted alone.
+    pub async fn count_deposits_in_range(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<RangeDepositCount, Eth1Error> {
+        let last = match block_height_range.end.checked_sub(1) {
+            Some(last) if block_height_range.start <= last => last,
+            _ => {
+                return Ok(RangeDepositCount {
+                    in_logs: 0,
+                    from_count: 0,
+                    matched: true,
+                })
+            }
+        };
+
+        let mut deposits = vec![];
+        for chunk in chunk_range(block_height_range.clone(), self.log_chunk_size()) {
+            let (_, chunk_deposits) = self.get_deposits_in_range(address, chunk).await?;
+            deposits.extend(chunk_deposits);
+        }
+        sort_and_dedup_deposits(&mut deposits);
+        let in_logs = deposits.len() as u64;
+
+        let from_count = self
+            .count_deposits_between(address, block_height_range.start, last)
+            .await?;
+
+        Ok(RangeDepositCount {
+            in_logs,
+            from_count,
+            matched: in_logs == from_count,
+        })
+    }
+
+    /// Returns `(block, count)` with the `get_deposit_count()` of `address` at each of `blocks`,
+    /// e.g., to chart the growth of deposits over time.
+    ///
+    /// The calls are sent as a single JSON-RPC batch, falling back to one request per block if
+    /// the endpoint does not support batches. The count is treated as zero at blocks before the
+    /// contract was deployed.
+    pub async fn deposit_count_history(
+        &self,
+        address: &str,
+        blocks: &[u64],
+    ) -> Result<Vec<(u64, u64)>, Eth1Error> {
+        if blocks.is_empty() {
+            return Ok(vec![]);
+        }
+        let params = blocks
+            .iter()
+            .map(|block| {
+                call_params(
+                    address,
+                    DEPOSIT_COUNT_FN_SIGNATURE,
+                    BlockQuery::Number(*block),
+                )
+            })
+            .collect();
+
+        let counts = match self.send_rpc_batch("eth_call", params).await {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| {
+                    let result = result?.ok_or("Deposit count response was none")?;
+                    Ok(decode_deposit_count(&decode::bytes(&result)?)?.unwrap_or(0))
+                })
+                .collect::<Result<Vec<_>, Eth1Error>>()?,
+            Err(_) => {
+                let mut counts = Vec::with_capacity(blocks.len());
+                for block in blocks {
+                    let count = self
+                        .get_deposit_count(address, BlockQuery::Number(*block))
+                        .await?
+                        .unwrap_or(0);
+                    counts.push(count);
+                }
+                counts
+            }
+        };
+
+        Ok(blocks.iter().copied().zip(counts).collect())
+    }
+
+    /// Returns the deposits made to `address` with an index of at least `since_index`, ordered by
+    /// deposit index.
+    ///
+    /// Rather than scanning the logs from the deployment of the contract, the block containing
+    /// deposit `since_index` is found by a binary search over `get_deposit_count()`, and only
+    /// the logs from that block onwards are fetched. Returns an empty list without fetching any
+    /// logs if there are no new deposits.
+    pub async fn fetch_new_deposits(
+        &self,
+        address: &str,
+        since_index: u64,
+    ) -> Result<Vec<DepositLog>, Eth1Error> {
+        let head = self.get_block_number().await?;
+        let count = self
+            .get_deposit_count(address, BlockQuery::Number(head))
+            .await?
+            .unwrap_or(0);
+        if count <= since_index {
+            return Ok(vec![]);
+        }
+
+        let low = self.find_deposit_block(address, since_index, head).await?;
+
+        let mut deposits = vec![];
+        for chunk in chunk_range(low..head + 1, self.log_chunk_size()) {
+            let (_, chunk_deposits) = self.get_deposits_in_range(address, chunk).await?;
+            deposits.extend(
+                chunk_deposits
+                    .into_iter()
+                    .filter(|deposit| deposit.index >= since_index),
+            );
+        }
+
+        sort_and_dedup_deposits(&mut deposits);
+
+        Ok(deposits)
+    }
+
+    /// Returns the block of the most recent deposit to `address`, or `None` if there have been
+    /// no deposits.
+    ///
+    /// The block is found by a binary search over `get_deposit_count()`, then confirmed by
+    /// fetching the logs of that block alone. A sync need not scan beyond this block, which
+    /// saves a lot of requests on a network with few deposits.
+    pub async fn last_deposit_block(&self, address: &str) -> Result<Option<u64>, Eth1Error> {
+        let head = self.get_block_number().await?;
+        let count = self
+            .get_deposit_count(address, BlockQuery::Number(head))
+            .await?
+            .unwrap_or(0);
+        let last_index = match count.checked_sub(1) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let block = self.find_deposit_block(address, last_index, head).await?;
+        let (_, deposits) = self
+            .get_deposits_in_range(address, block..block + 1)
+            .await?;
+        if deposits.iter().any(|deposit| deposit.index == last_index) {
+            Ok(Some(block))
+        } else {
+            Err(Eth1Error::InvalidResponse(format!(
+                "Deposit {} was not in the logs of block {}",
+                last_index, block
+            )))
+        }
+    }
+
+    /// Returns an error if the logs of `block_height_range` appear to have been pruned by the
+    /// endpoint.
+    ///
+    /// Some nodes silently return no logs for old blocks, which would otherwise look like a range
+    /// without deposits. The `get_deposit_count()` at either end of the range shows whether any
+    /// deposits are expected; if so, the block of the first one is found by a binary search and
+    /// its logs are fetched to check that the deposit is there. Note that a node which has also
+    /// pruned the state of the range will fail the `get_deposit_count()` calls instead.
+    pub async fn check_range_available(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<(), Eth1Error> {
+        let last = match block_height_range.end.checked_sub(1) {
+            Some(last) if block_height_range.start <= last => last,
+            _ => return Ok(()),
+        };
+
+        let count_at_end = self
+            .get_deposit_count(address, BlockQuery::Number(last))
+            .await?
+            .unwrap_or(0);
+        let count_before_start = match block_height_range.start.checked_sub(1) {
+            Some(before) => self
+                .get_deposit_count(address, BlockQuery::Number(before))
+                .await?
+                .unwrap_or(0),
+            None => 0,
+        };
+        if count_at_end <= count_before_start {
+            return Ok(());
+        }
+
+        let index = count_before_start;
+        let block = self.find_deposit_block(address, index, last).await?;
+        let (_, deposits) = self
+            .get_deposits_in_range(address, block..block + 1)
+            .await?;
+        if deposits.iter().any(|deposit| deposit.index == index) {
+            Ok(())
+        } else {
+            Err(Eth1Error::InvalidResponse(format!(
+                "Deposit {} was not in the logs of block {}, range may be pruned; use an archive node",
+                index, block
+            )))
+        }
+    }
+
+    /// Returns the first block at or before `head` at which deposit `index` exists, by a binary
+    /// search over `get_deposit_count()`.
+    ///
+    /// The deposit must exist at `head`.
+    async fn find_deposit_block(
+        &self,
+        address: &str,
+        index: u64,
+        head: u64,
+    ) -> Result<u64, Eth1Error> {
+        let mut low = 0;
+        let mut high = head;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let count_at_mid = self
+                .get_deposit_count(address, BlockQuery::Number(mid))
+                .await?
+                .unwrap_or(0);
+            if count_at_mid > index {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Ok(low)
+    }
+}
+
+/// Returns the number of deposits made to `address` in blocks `start..=end` (inclusive), using two
+/// `get_deposit_count()` calls.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn count_deposits_between(
+    endpoint: &str,
+    address: &str,
+    start: u64,
+    end: u64,
+    timeout: Duration,
+) -> Result<u64, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .count_deposits_between(address, start, end)
+        .await
+}
+
+/// Counts the deposits made to `address` in `block_height_range` from both its logs and the
+/// `get_deposit_count()` at either end of it.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn count_deposits_in_range(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<RangeDepositCount, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .count_deposits_in_range(address, block_height_range)
+        .await
+}
+
+/// Returns `(block, count)` with the `get_deposit_count()` of `address` at each of `blocks`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn deposit_count_history(
+    endpoint: &str,
+    address: &str,
+    blocks: &[u64],
+    timeout: Duration,
+) -> Result<Vec<(u64, u64)>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .deposit_count_history(address, blocks)
+        .await
+}
+
+/// Returns the deposits made to `address` with an index of at least `since_index`, ordered by
+/// deposit index.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn fetch_new_deposits(
+    endpoint: &str,
+    address: &str,
+    since_index: u64,
+    timeout: Duration,
+) -> Result<Vec<DepositLog>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .fetch_new_deposits(address, since_index)
+        .await
+}
+
+/// Returns an error if the logs of `block_height_range` appear to have been pruned by the
+/// endpoint.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn check_range_available(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<(), Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .check_range_available(address, block_height_range)
+        .await
+}
+
+/// Returns the block of the most recent deposit to `address`, or `None` if there have been no
+/// deposits.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn last_deposit_block(
+    endpoint: &str,
+    address: &str,
+    timeout: Duration,
+) -> Result<Option<u64>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .last_deposit_block(address)
+        .await
+}
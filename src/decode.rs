@@ -0,0 +1,135 @@
+//! Decoding of the hex-encoded quantities and data found in JSON-RPC responses.
+//!
+//! The functions which take a `Value` expect it to be a `0x`-prefixed hex string, whilst the
+//! `hex_to_*` functions operate on a string that has already been extracted (e.g., by `serde`).
+//!
+//! All errors are `String` and describe what was expected, leaving the caller to add context.
+//!
+//! Most of these are only used when parsing responses from the HTTP client.
+#![cfg_attr(not(feature = "http"), allow(dead_code))]
+
+use ethereum_types::{H160, H256 as Hash256, U256};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Returns the field `name` of the object `value`, treating `null` as absent.
+pub(crate) fn optional<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value.get(name) {
+        None | Some(Value::Null) => None,
+        Some(field) => Some(field),
+    }
+}
+
+/// Returns the field `name` of the object `value`, or an error if it is absent or `null`.
+pub(crate) fn required<'a>(value: &'a Value, name: &str) -> Result<&'a Value, String> {
+    optional(value, name).ok_or_else(|| format!("No {} field", name))
+}
+
+/// Returns `value` as a string, or an error if it is any other type.
+pub(crate) fn string(value: &Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("Expected hex string, found {}", value))
+}
+
+/// Parses a hex-encoded quantity as a `u64`.
+pub(crate) fn quantity(value: &Value) -> Result<u64, String> {
+    hex_to_u64_be(string(value)?)
+}
+
+/// Parses a hex-encoded quantity as a `U256`.
+pub(crate) fn quantity_u256(value: &Value) -> Result<U256, String> {
+    U256::from_str(strip_prefix(string(value)?)?)
+        .map_err(|e| format!("Failed to parse hex as U256: {:?}", e))
+}
+
+/// Parses hex-encoded data as bytes.
+pub(crate) fn bytes(value: &Value) -> Result<Vec<u8>, String> {
+    hex_to_bytes(string(value)?)
+}
+
+/// Parses hex-encoded data as a 32-byte hash.
+pub(crate) fn hash(value: &Value) -> Result<Hash256, String> {
+    hex_to_hash(string(value)?)
+}
+
+/// Parses hex-encoded data as a 20-byte address.
+pub(crate) fn address(value: &Value) -> Result<H160, String> {
+    hex_to_address(string(value)?)
+}
+
+/// Parses a `0x`-prefixed, **big-endian** hex string as a u64.
+///
+/// Note: the JSON-RPC encodes integers as big-endian. The deposit contract uses little-endian.
+/// Therefore, this function is only useful for numbers encoded by the JSON RPC.
+///
+/// E.g., `0x01 == 1`
+pub(crate) fn hex_to_u64_be(hex: &str) -> Result<u64, String> {
+    u64::from_str_radix(strip_prefix(hex)?, 16)
+        .map_err(|e| format!("Failed to parse hex as u64: {:?}", e))
+}
+
+/// Parses a `0x`-prefixed hex string as bytes.
+pub(crate) fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    hex::decode(strip_prefix(hex)?).map_err(|e| format!("Failed to parse hex as bytes: {:?}", e))
+}
+
+/// Parses a `0x`-prefixed hex string as a 32-byte hash.
+pub(crate) fn hex_to_hash(hex: &str) -> Result<Hash256, String> {
+    let bytes = hex_to_bytes(hex)?;
+    if bytes.len() == 32 {
+        Ok(Hash256::from_slice(&bytes))
+    } else {
+        Err(format!("Hash was {} bytes, expected 32", bytes.len()))
+    }
+}
+
+/// Parses a `0x`-prefixed hex string as a 20-byte address.
+pub(crate) fn hex_to_address(hex: &str) -> Result<H160, String> {
+    let bytes = hex_to_bytes(hex)?;
+    if bytes.len() == 20 {
+        Ok(H160::from_slice(&bytes))
+    } else {
+        Err(format!("Address was {} bytes, expected 20", bytes.len()))
+    }
+}
+
+/// Removes the `0x` prefix from some bytes. Returns an error if the prefix is not present.
+pub(crate) fn strip_prefix(hex: &str) -> Result<&str, String> {
+    hex.strip_prefix("0x")
+        .ok_or_else(|| "Hex string did not start with `0x`".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn quantities_are_big_endian() {
+        assert_eq!(hex_to_u64_be("0x0"), Ok(0));
+        assert_eq!(hex_to_u64_be("0x1"), Ok(1));
+        assert_eq!(hex_to_u64_be("0x391f63"), Ok(3743587));
+        assert_eq!(quantity(&json!("0x100")), Ok(256));
+        assert!(hex_to_u64_be("0x").is_err());
+        assert!(hex_to_u64_be("0x10000000000000000").is_err());
+    }
+
+    #[test]
+    fn hex_requires_a_prefix() {
+        assert_eq!(hex_to_bytes("0xdead"), Ok(vec![0xde, 0xad]));
+        assert!(hex_to_bytes("dead").is_err());
+        assert!(string(&json!(1)).is_err());
+    }
+
+    #[test]
+    fn hashes_and_addresses_have_fixed_lengths() {
+        let hash = format!("0x{}", "11".repeat(32));
+        assert_eq!(hex_to_hash(&hash), Ok(Hash256::repeat_byte(0x11)));
+        assert!(hex_to_hash("0x11").is_err());
+
+        let address = format!("0x{}", "22".repeat(20));
+        assert_eq!(hex_to_address(&address), Ok(H160::repeat_byte(0x22)));
+        assert!(hex_to_address(&hash).is_err());
+    }
+}
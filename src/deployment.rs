@@ -0,0 +1,67 @@
+//! Finding the block in which a contract was deployed.
+
+use crate::client::{block_query_param, response_result};
+use crate::{decode, BlockQuery, Eth1Client, Eth1Error};
+use serde_json::json;
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Returns the code of the contract at `address` at `block`, which is empty if there is no
+    /// contract there.
+    pub async fn get_code(&self, address: &str, block: BlockQuery) -> Result<Vec<u8>, Eth1Error> {
+        let params = json!([address, block_query_param(block)]);
+
+        let response_body = self.send_rpc_request("eth_getCode", params).await?;
+        decode::bytes(&response_result(&response_body)?.ok_or("No result was returned for code")?)
+            .map_err(|e| Eth1Error::InvalidResponse(format!("Failed to get code: {}", e)))
+    }
+
+    /// Returns the number of the block in which the contract at `address` was deployed.
+    ///
+    /// The code at `address` is empty before deployment and non-empty after, so this is a binary
+    /// search over `eth_getCode`, requesting about `log2(head)` blocks. The endpoint must be an
+    /// archive node, since other nodes cannot return the code at old blocks.
+    pub async fn find_deployment_block(&self, address: &str) -> Result<u64, Eth1Error> {
+        let head = self.get_block_number().await?;
+        if self
+            .get_code(address, BlockQuery::Number(head))
+            .await?
+            .is_empty()
+        {
+            return Err(Eth1Error::InvalidRequest(format!(
+                "No contract is deployed at {}",
+                address
+            )));
+        }
+
+        let mut low = 0;
+        let mut high = head;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self
+                .get_code(address, BlockQuery::Number(mid))
+                .await?
+                .is_empty()
+            {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low)
+    }
+}
+
+/// Returns the number of the block in which the contract at `address` was deployed.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn find_deployment_block(
+    endpoint: &str,
+    address: &str,
+    timeout: Duration,
+) -> Result<u64, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .find_deployment_block(address)
+        .await
+}
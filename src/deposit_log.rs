@@ -0,0 +1,456 @@
+//! Decoding of the `DepositEvent` logs emitted by the deposit contract.
+//!
+//! The event is `DepositEvent(bytes,bytes,bytes,bytes,bytes)`, so each field is ABI-encoded as
+//! a dynamic `bytes` value. The data has a fixed layout since each field has a fixed length,
+//! so the offsets of each value are hard-coded here.
+
+use crate::decode::hex_to_hash;
+use crate::tree_hash::{merkleize, pack, u64_chunk};
+use crate::{
+    CheckError, Log, PublicKey, Signature, WithdrawalCredentials, COMPOUNDING_WITHDRAWAL_PREFIX,
+    DEPOSIT_EVENT_TOPIC,
+};
+use ethereum_types::{H160, H256 as Hash256};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub const PUBKEY_START: usize = 192;
+pub const PUBKEY_LEN: usize = 48;
+pub const CREDS_START: usize = PUBKEY_START + 64 + 32;
+pub const CREDS_LEN: usize = 32;
+pub const AMOUNT_START: usize = CREDS_START + 32 + 32;
+pub const AMOUNT_LEN: usize = 8;
+pub const SIG_START: usize = AMOUNT_START + 32 + 32;
+pub const SIG_LEN: usize = 96;
+pub const INDEX_START: usize = SIG_START + 96 + 32;
+pub const INDEX_LEN: usize = 8;
+
+/// Number of gwei in one ether.
+pub const GWEI_PER_ETH: u64 = 1_000_000_000;
+/// The smallest deposit accepted by the deposit contract, in gwei.
+pub const MIN_DEPOSIT_AMOUNT: u64 = GWEI_PER_ETH;
+/// The most a validator with BLS (`0x00`) or eth1 address (`0x01`) withdrawal credentials
+/// can have as its effective balance, in gwei.
+pub const MAX_EFFECTIVE_BALANCE: u64 = 32 * GWEI_PER_ETH;
+/// The most a validator with compounding (`0x02`) withdrawal credentials can have as its
+/// effective balance, in gwei.
+pub const MAX_EFFECTIVE_BALANCE_ELECTRA: u64 = 2048 * GWEI_PER_ETH;
+
+/// A deposit, as decoded from a `DepositEvent` log.
+///
+/// Serializes with byte fields as `0x`-prefixed hex and the amount in gwei.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct DepositLog {
+    pub pubkey: PublicKey,
+    pub withdrawal_credentials: WithdrawalCredentials,
+    /// The deposit amount, in gwei.
+    pub amount: u64,
+    pub signature: Signature,
+    /// The index of the deposit in the deposit contract's merkle tree.
+    pub index: u64,
+    /// The deposit contract which emitted the log.
+    pub address: H160,
+    /// The block in which the deposit was included.
+    pub block_number: u64,
+    /// The transaction which made the deposit.
+    pub transaction_hash: Hash256,
+    /// `true` if the deposit was in a block which has since been re-orged out, so it should be
+    /// rolled back.
+    pub removed: bool,
+    /// The timestamp of the block, in unix seconds. Logs do not include it, so it is `None`
+    /// unless attached by `Eth1Client::attach_timestamps`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deposited_at: Option<u64>,
+}
+
+impl DepositLog {
+    /// Returns the deposit amount, in gwei.
+    pub fn amount_gwei(&self) -> u64 {
+        self.amount
+    }
+
+    /// Returns the deposit amount, in ether. May be imprecise for amounts with many decimal
+    /// places, see `Self::amount_eth_string` for an exact value.
+    pub fn amount_eth(&self) -> f64 {
+        self.amount as f64 / GWEI_PER_ETH as f64
+    }
+
+    /// Returns the exact deposit amount in ether as a decimal string, e.g., `32.0` or `1.5`.
+    pub fn amount_eth_string(&self) -> String {
+        let whole = self.amount / GWEI_PER_ETH;
+        let fraction = format!("{:09}", self.amount % GWEI_PER_ETH);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            format!("{}.0", whole)
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    }
+
+    /// Returns `true` if the withdrawal credentials have an eth1 address prefix (`0x01` or
+    /// `0x02`) and withdraw to `address`.
+    ///
+    /// Returns `false` for BLS (`0x00`) credentials, which can never match an address.
+    pub fn has_withdrawal_address(&self, address: H160) -> bool {
+        self.withdrawal_credentials.eth1_address() == Some(address)
+    }
+
+    /// Returns an error if the amount is below `MIN_DEPOSIT_AMOUNT`.
+    ///
+    /// The contract rejects deposits below the minimum, so such a log indicates corrupt data.
+    /// The contract also rejects amounts which are not a whole number of gwei, which the log
+    /// cannot express. Larger amounts are valid, see `Self::exceeds_max_effective_balance`.
+    pub fn validate_amount(&self) -> Result<(), CheckError> {
+        if self.amount >= MIN_DEPOSIT_AMOUNT {
+            Ok(())
+        } else {
+            Err(CheckError::InvalidAmount {
+                index: self.index,
+                amount: self.amount,
+                min: MIN_DEPOSIT_AMOUNT,
+            })
+        }
+    }
+
+    /// Returns the max effective balance for the withdrawal credentials, in gwei:
+    /// `MAX_EFFECTIVE_BALANCE_ELECTRA` for compounding credentials, `MAX_EFFECTIVE_BALANCE` for
+    /// any other.
+    pub fn max_effective_balance(&self) -> u64 {
+        if self.withdrawal_credentials.prefix() == COMPOUNDING_WITHDRAWAL_PREFIX {
+            MAX_EFFECTIVE_BALANCE_ELECTRA
+        } else {
+            MAX_EFFECTIVE_BALANCE
+        }
+    }
+
+    /// Returns `true` if the amount is above `Self::max_effective_balance`.
+    ///
+    /// The contract accepts such a deposit, but any balance above the max effective balance is
+    /// withdrawn again, so it is unexpected rather than invalid.
+    pub fn exceeds_max_effective_balance(&self) -> bool {
+        self.amount > self.max_effective_balance()
+    }
+}
+
+/// The contract whose deposits are tracked, and the topic of the event it emits for each.
+///
+/// Other deposit-like contracts (e.g., of staking pools or bridges) may name their event
+/// differently, and so have a different topic, but must emit the same data as the canonical
+/// `DepositEvent` for their logs to be decoded.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DepositConfig {
+    pub contract: String,
+    /// The first topic of each deposit log, as a `0x`-prefixed hex string.
+    pub topic: String,
+}
+
+impl DepositConfig {
+    /// Returns the config for a `contract` which emits the canonical `DEPOSIT_EVENT_TOPIC`.
+    pub fn new(contract: &str) -> Self {
+        Self {
+            contract: contract.to_string(),
+            topic: DEPOSIT_EVENT_TOPIC.to_string(),
+        }
+    }
+
+    /// Returns the config for a `contract` which emits deposits with the given `topic`.
+    ///
+    /// Returns an error if `topic` is not a `0x`-prefixed 32-byte hex string.
+    pub fn with_topic(contract: &str, topic: &str) -> Result<Self, String> {
+        hex_to_hash(topic).map_err(|e| format!("Invalid topic {}: {}", topic, e))?;
+        Ok(Self {
+            contract: contract.to_string(),
+            topic: topic.to_string(),
+        })
+    }
+}
+
+/// Returns an error if the first topic of `log` is not `topic`, e.g., `DEPOSIT_EVENT_TOPIC`.
+pub(crate) fn check_deposit_topic(log: &Log, topic: &str) -> Result<(), String> {
+    let deposit_event_topic = hex_to_hash(topic)?;
+    match log.topics.first() {
+        Some(first) if *first == deposit_event_topic => Ok(()),
+        Some(first) => Err(format!(
+            "Log topic {:?} is not the deposit topic {}",
+            first, topic
+        )),
+        None => Err("Log has no topics, expected the deposit topic".to_string()),
+    }
+}
+
+/// Decodes the data of a `DepositEvent` log.
+///
+/// Returns an error if the first topic of the log is not `DEPOSIT_EVENT_TOPIC`, e.g., because a
+/// misconfigured filter returned some other event, or if the log data is too short to contain
+/// each of the fields.
+pub fn decode_deposit_log(log: &Log) -> Result<DepositLog, String> {
+    decode_deposit_log_with_topic(log, DEPOSIT_EVENT_TOPIC)
+}
+
+/// As per `decode_deposit_log`, but for a contract whose deposit event has the given `topic`,
+/// see `DepositConfig`.
+pub fn decode_deposit_log_with_topic(log: &Log, topic: &str) -> Result<DepositLog, String> {
+    check_deposit_topic(log, topic)?;
+
+    let bytes = &log.data;
+
+    let pubkey = bytes
+        .get(PUBKEY_START..PUBKEY_START + PUBKEY_LEN)
+        .ok_or("Insufficient bytes for pubkey")?;
+    let withdrawal_credentials = bytes
+        .get(CREDS_START..CREDS_START + CREDS_LEN)
+        .ok_or("Insufficient bytes for withdrawal credential")?;
+    let amount = bytes
+        .get(AMOUNT_START..AMOUNT_START + AMOUNT_LEN)
+        .ok_or("Insufficient bytes for amount")?;
+    let signature = bytes
+        .get(SIG_START..SIG_START + SIG_LEN)
+        .ok_or("Insufficient bytes for signature")?;
+    let index = bytes
+        .get(INDEX_START..INDEX_START + INDEX_LEN)
+        .ok_or("Insufficient bytes for index")?;
+
+    Ok(DepositLog {
+        pubkey: PublicKey::from_slice(pubkey)?,
+        withdrawal_credentials: WithdrawalCredentials::from_slice(withdrawal_credentials)?,
+        amount: u64_from_le_slice(amount),
+        signature: Signature::from_slice(signature)?,
+        index: u64_from_le_slice(index),
+        address: log.address,
+        block_number: log.block_number,
+        transaction_hash: log.transaction_hash,
+        removed: log.removed,
+        deposited_at: None,
+    })
+}
+
+/// Groups `deposits` by the block they were included in, e.g., to build the deposit tree one
+/// block at a time.
+///
+/// The deposits in each block keep the order they had in `deposits`, which is log order for the
+/// output of `eth_getLogs`.
+pub fn group_by_block<I: IntoIterator<Item = DepositLog>>(
+    deposits: I,
+) -> BTreeMap<u64, Vec<DepositLog>> {
+    let mut blocks: BTreeMap<u64, Vec<DepositLog>> = BTreeMap::new();
+    for deposit in deposits {
+        blocks
+            .entry(deposit.block_number)
+            .or_default()
+            .push(deposit);
+    }
+    blocks
+}
+
+/// Sorts `deposits` by deposit index and removes all but the first deposit with each index.
+///
+/// Guards against a node which returns the same log twice.
+#[cfg(feature = "http")]
+pub(crate) fn sort_and_dedup_deposits(deposits: &mut Vec<DepositLog>) {
+    deposits.sort_by_key(|deposit| deposit.index);
+    deposits.dedup_by_key(|deposit| deposit.index);
+}
+
+/// The consensus-spec `DepositData` container.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DepositData {
+    pub pubkey: [u8; PUBKEY_LEN],
+    pub withdrawal_credentials: Hash256,
+    /// The deposit amount, in gwei.
+    pub amount: u64,
+    pub signature: [u8; SIG_LEN],
+}
+
+impl DepositData {
+    /// Returns the SSZ hash tree root of `self`.
+    ///
+    /// This is the value passed as `deposit_data_root` to the deposit contract and is the leaf
+    /// of the deposit tree.
+    pub fn tree_hash_root(&self) -> Hash256 {
+        merkleize(&[
+            merkleize(&pack(&self.pubkey)).to_fixed_bytes(),
+            self.withdrawal_credentials.to_fixed_bytes(),
+            u64_chunk(self.amount),
+            merkleize(&pack(&self.signature)).to_fixed_bytes(),
+        ])
+    }
+
+    /// Returns the SSZ hash tree root of the `DepositMessage` for `self`, i.e., the deposit
+    /// without its signature. This is the message which the signature signs (once mixed with
+    /// the domain).
+    pub fn deposit_message_root(&self) -> Hash256 {
+        merkleize(&[
+            merkleize(&pack(&self.pubkey)).to_fixed_bytes(),
+            self.withdrawal_credentials.to_fixed_bytes(),
+            u64_chunk(self.amount),
+        ])
+    }
+}
+
+impl From<&DepositLog> for DepositData {
+    fn from(log: &DepositLog) -> Self {
+        Self {
+            pubkey: log.pubkey.0,
+            withdrawal_credentials: Hash256(log.withdrawal_credentials.0),
+            amount: log.amount,
+            signature: log.signature.0,
+        }
+    }
+}
+
+/// Reads a little-endian `u64` from exactly 8 bytes.
+fn u64_from_le_slice(bytes: &[u8]) -> u64 {
+    let mut array = [0; 8];
+    array.copy_from_slice(bytes);
+    u64::from_le_bytes(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        deposit, deposit_log_json, logs_response, DEPOSIT_DATA_FILE, DEPOSIT_DATA_ROOT,
+        DEPOSIT_MESSAGE_ROOT,
+    };
+    use crate::{decode_logs_response, Log};
+    use serde_json::{json, Value};
+
+    fn deposit_of(amount: u64) -> DepositLog {
+        DepositLog {
+            amount,
+            ..deposit(0, 0)
+        }
+    }
+
+    #[test]
+    fn whole_ether_amounts() {
+        let deposit = deposit_of(32_000_000_000);
+        assert_eq!(deposit.amount_gwei(), 32_000_000_000);
+        assert_eq!(deposit.amount_eth(), 32.0);
+        assert_eq!(deposit.amount_eth_string(), "32.0");
+    }
+
+    #[test]
+    fn fractional_ether_amounts() {
+        assert_eq!(deposit_of(1_500_000_000).amount_eth(), 1.5);
+        assert_eq!(deposit_of(1_500_000_000).amount_eth_string(), "1.5");
+        assert_eq!(deposit_of(1).amount_eth_string(), "0.000000001");
+        assert_eq!(deposit_of(0).amount_eth_string(), "0.0");
+    }
+
+    #[test]
+    fn minimum_amount_is_one_ether() {
+        assert!(deposit_of(MIN_DEPOSIT_AMOUNT).validate_amount().is_ok());
+        assert_eq!(
+            deposit_of(MIN_DEPOSIT_AMOUNT - 1).validate_amount(),
+            Err(CheckError::InvalidAmount {
+                index: 0,
+                amount: 999_999_999,
+                min: 1_000_000_000,
+            })
+        );
+        assert!(deposit_of(0).validate_amount().is_err());
+    }
+
+    #[test]
+    fn amounts_above_32_ether_are_unexpected() {
+        let at_max = deposit_of(32 * GWEI_PER_ETH);
+        assert_eq!(at_max.max_effective_balance(), MAX_EFFECTIVE_BALANCE);
+        assert!(!at_max.exceeds_max_effective_balance());
+
+        let above_max = deposit_of(32 * GWEI_PER_ETH + 1);
+        assert!(above_max.validate_amount().is_ok());
+        assert!(above_max.exceeds_max_effective_balance());
+    }
+
+    #[test]
+    fn compounding_credentials_allow_2048_ether() {
+        let mut credentials = [0xbb; 32];
+        credentials[0] = COMPOUNDING_WITHDRAWAL_PREFIX;
+        let compounding = |amount| DepositLog {
+            withdrawal_credentials: WithdrawalCredentials(credentials),
+            ..deposit_of(amount)
+        };
+
+        let at_max = compounding(2048 * GWEI_PER_ETH);
+        assert_eq!(
+            at_max.max_effective_balance(),
+            MAX_EFFECTIVE_BALANCE_ELECTRA
+        );
+        assert!(!at_max.exceeds_max_effective_balance());
+        assert!(!compounding(33 * GWEI_PER_ETH).exceeds_max_effective_balance());
+
+        let above_max = compounding(2048 * GWEI_PER_ETH + 1);
+        assert!(above_max.validate_amount().is_ok());
+        assert!(above_max.exceeds_max_effective_balance());
+    }
+
+    /// Returns the bytes of the hex field `name` of the entry in `DEPOSIT_DATA_FILE`.
+    fn fixture_field(name: &str) -> Vec<u8> {
+        let entries: Value = serde_json::from_str(DEPOSIT_DATA_FILE).unwrap();
+        hex::decode(entries[0][name].as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn roots_match_the_deposit_data_file() {
+        let mut data = DepositData {
+            pubkey: [0; PUBKEY_LEN],
+            withdrawal_credentials: Hash256::from_slice(&fixture_field("withdrawal_credentials")),
+            amount: 32_000_000_000,
+            signature: [0; SIG_LEN],
+        };
+        data.pubkey.copy_from_slice(&fixture_field("pubkey"));
+        data.signature.copy_from_slice(&fixture_field("signature"));
+
+        assert_eq!(
+            hex::encode(data.deposit_message_root()),
+            DEPOSIT_MESSAGE_ROOT
+        );
+        assert_eq!(hex::encode(data.tree_hash_root()), DEPOSIT_DATA_ROOT);
+    }
+
+    /// Returns the log of `deposit(0, 5)`, with the given `topics`.
+    fn log_with_topics(topics: Value) -> Log {
+        let mut log = deposit_log_json(&deposit(0, 5), 0);
+        log["topics"] = topics;
+        decode_logs_response(&logs_response(vec![log]))
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn deposit_topic_is_accepted() {
+        let log = log_with_topics(json!([DEPOSIT_EVENT_TOPIC]));
+        assert!(check_deposit_topic(&log, DEPOSIT_EVENT_TOPIC).is_ok());
+        assert_eq!(decode_deposit_log(&log).unwrap(), deposit(0, 5));
+    }
+
+    #[test]
+    fn other_topics_are_rejected() {
+        let transfer = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+        let log = log_with_topics(json!([transfer, DEPOSIT_EVENT_TOPIC]));
+        assert!(check_deposit_topic(&log, DEPOSIT_EVENT_TOPIC).is_err());
+        assert!(decode_deposit_log(&log).is_err());
+    }
+
+    #[test]
+    fn logs_without_topics_are_rejected() {
+        let log = log_with_topics(json!([]));
+        assert_eq!(
+            decode_deposit_log(&log),
+            Err("Log has no topics, expected the deposit topic".to_string())
+        );
+    }
+
+    #[test]
+    fn custom_topics_are_accepted() {
+        let custom = format!("0x{}", "ab".repeat(32));
+        let log = log_with_topics(json!([custom]));
+        assert!(decode_deposit_log(&log).is_err());
+        assert_eq!(
+            decode_deposit_log_with_topic(&log, &custom).unwrap(),
+            deposit(0, 5)
+        );
+        assert!(decode_deposit_log_with_topic(&log, "0xab").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! The deposit contract's merkle tree of `DepositData` roots.
+
+use crate::tree_hash::{hash32_concat, u64_chunk};
+use crate::{CheckError, DepositData, DepositLog};
+use ethereum_types::H256 as Hash256;
+use std::convert::TryFrom;
+
+/// Depth of the deposit contract's merkle tree, i.e., it has room for `2**32` deposits.
+pub const DEPOSIT_CONTRACT_TREE_DEPTH: usize = 32;
+
+/// The deposit tree, built from the deposit logs.
+///
+/// Like the deposit contract, only the branch needed to append the next leaf is kept, so each
+/// push is `O(DEPOSIT_CONTRACT_TREE_DEPTH)` rather than a rebuild of the tree. The root after
+/// each push is kept too, see `Self::root_at_count`.
+///
+/// The root matches the contract's `get_deposit_root()` once every deposit up to that point has
+/// been pushed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DepositTree {
+    branch: [[u8; 32]; DEPOSIT_CONTRACT_TREE_DEPTH],
+    zero_hashes: [[u8; 32]; DEPOSIT_CONTRACT_TREE_DEPTH + 1],
+    /// The root after each push, starting with the root of the empty tree.
+    roots: Vec<Hash256>,
+}
+
+impl Default for DepositTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DepositTree {
+    /// Returns a tree with no deposits.
+    pub fn new() -> Self {
+        let mut tree = Self {
+            branch: [[0; 32]; DEPOSIT_CONTRACT_TREE_DEPTH],
+            zero_hashes: zero_hashes(),
+            roots: vec![],
+        };
+        tree.roots.push(tree.compute_root(0));
+        tree
+    }
+
+    /// Returns a tree of `deposits`, which must be ordered by index starting at `0`.
+    pub fn from_deposits(deposits: &[DepositLog]) -> Result<Self, CheckError> {
+        let mut tree = Self::new();
+        for deposit in deposits {
+            tree.push(deposit)?;
+        }
+        Ok(tree)
+    }
+
+    /// Appends `deposit` to the tree, updating the root.
+    ///
+    /// Returns an error if `deposit` is not the next deposit, i.e., its index is not `self.len()`.
+    pub fn push(&mut self, deposit: &DepositLog) -> Result<(), CheckError> {
+        let expected = self.len();
+        if deposit.index != expected {
+            return Err(CheckError::DepositGap {
+                expected,
+                found: deposit.index,
+            });
+        }
+
+        let mut node = DepositData::from(deposit).tree_hash_root().to_fixed_bytes();
+        let mut size = expected + 1;
+        for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+            if size & 1 == 1 {
+                self.branch[height] = node;
+                break;
+            }
+            node = hash32_concat(&self.branch[height], &node);
+            size >>= 1;
+        }
+
+        let root = self.compute_root(expected + 1);
+        self.roots.push(root);
+        Ok(())
+    }
+
+    /// Returns the number of deposits in the tree.
+    pub fn len(&self) -> u64 {
+        self.roots.len() as u64 - 1
+    }
+
+    /// Returns `true` if the tree has no deposits.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the root of the tree with the deposit count mixed in, as returned by the
+    /// contract's `get_deposit_root()`.
+    pub fn root(&self) -> Hash256 {
+        self.roots[self.roots.len() - 1]
+    }
+
+    /// Returns the root of the tree when it held the first `count` deposits, or `None` if
+    /// `count > self.len()`.
+    pub fn root_at_count(&self, count: u64) -> Option<Hash256> {
+        self.roots.get(usize::try_from(count).ok()?).copied()
+    }
+
+    /// Computes the root from `self.branch`, which must hold `count` deposits.
+    fn compute_root(&self, count: u64) -> Hash256 {
+        let mut node = [0; 32];
+        let mut size = count;
+        for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+            node = if size & 1 == 1 {
+                hash32_concat(&self.branch[height], &node)
+            } else {
+                hash32_concat(&node, &self.zero_hashes[height])
+            };
+            size >>= 1;
+        }
+        Hash256::from_slice(&hash32_concat(&node, &u64_chunk(count)))
+    }
+}
+
+/// Returns the root of an empty subtree of each height, from `0` (a zero leaf) to
+/// `DEPOSIT_CONTRACT_TREE_DEPTH`.
+fn zero_hashes() -> [[u8; 32]; DEPOSIT_CONTRACT_TREE_DEPTH + 1] {
+    let mut hashes = [[0; 32]; DEPOSIT_CONTRACT_TREE_DEPTH + 1];
+    for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+        hashes[height + 1] = hash32_concat(&hashes[height], &hashes[height]);
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deposit;
+
+    /// Returns the root of a tree of `deposits`, hashing every layer of the whole tree rather
+    /// than keeping a branch.
+    fn full_root(deposits: &[DepositLog]) -> Hash256 {
+        let mut layer: Vec<[u8; 32]> = deposits
+            .iter()
+            .map(|deposit| DepositData::from(deposit).tree_hash_root().to_fixed_bytes())
+            .collect();
+        for zero_hash in zero_hashes().iter().take(DEPOSIT_CONTRACT_TREE_DEPTH) {
+            if layer.len() % 2 == 1 {
+                layer.push(*zero_hash);
+            }
+            layer = layer
+                .chunks(2)
+                .map(|pair| hash32_concat(&pair[0], &pair[1]))
+                .collect();
+        }
+        let node = layer
+            .first()
+            .copied()
+            .unwrap_or(zero_hashes()[DEPOSIT_CONTRACT_TREE_DEPTH]);
+        Hash256::from_slice(&hash32_concat(&node, &u64_chunk(deposits.len() as u64)))
+    }
+
+    #[test]
+    fn empty_tree_has_the_contract_root() {
+        // The contract's `get_deposit_root()` before the first deposit.
+        let expected: Hash256 = "d70a234731285c6804c2a4f56711ddb8c82c99740f207854891028af34e27e5e"
+            .parse()
+            .unwrap();
+        assert_eq!(DepositTree::new().root(), expected);
+        assert_eq!(full_root(&[]), expected);
+    }
+
+    #[test]
+    fn pushing_one_by_one_matches_a_full_tree() {
+        let deposits: Vec<DepositLog> = (0..9).map(|index| deposit(index, index)).collect();
+        let mut tree = DepositTree::new();
+        for (count, deposit) in deposits.iter().enumerate() {
+            tree.push(deposit).unwrap();
+            assert_eq!(tree.root(), full_root(&deposits[..=count]), "{}", count);
+        }
+        for count in 0..=deposits.len() {
+            assert_eq!(
+                tree.root_at_count(count as u64),
+                Some(full_root(&deposits[..count]))
+            );
+        }
+        assert_eq!(tree.root_at_count(10), None);
+    }
+
+    #[test]
+    fn rejects_a_deposit_out_of_order() {
+        let mut tree = DepositTree::new();
+        tree.push(&deposit(0, 1)).unwrap();
+        assert_eq!(
+            tree.push(&deposit(2, 1)),
+            Err(CheckError::DepositGap {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(tree.len(), 1);
+    }
+}
@@ -0,0 +1,297 @@
+//! Errors returned when interacting with an eth1 endpoint, and when checking the data it
+//! returns.
+
+use ethereum_types::H256 as Hash256;
+#[cfg(feature = "http")]
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// An error from an eth1 JSON-RPC request.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Eth1Error {
+    /// The endpoint could not be reached, e.g., its hostname did not resolve or the connection
+    /// was refused.
+    Connection(String),
+    /// The HTTP request failed for some reason other than connecting.
+    Request(String),
+    /// The response had an HTTP status other than 200 OK.
+    HttpStatus(u16),
+    /// The endpoint returned a JSON-RPC error object.
+    Rpc { code: i64, message: String },
+    /// The response could not be decoded.
+    InvalidResponse(String),
+    /// The request was rejected before being sent, e.g., because its block range was too wide.
+    InvalidRequest(String),
+    /// The requested block is pending, so it has no hash or number yet.
+    PendingBlock,
+    /// No response was received within the timeout, whether detected by `reqwest` or by the
+    /// timeout around each request attempt.
+    Timeout,
+    /// The head block is older than allowed, so the node may be stuck or on a dead network.
+    StaleHead { block_number: u64, age: Duration },
+    /// The response body was abandoned once it exceeded `limit` bytes, as per
+    /// `ClientConfig::max_response_bytes`.
+    ResponseTooLarge { limit: usize },
+}
+
+/// The JSON-RPC error code for a method which the endpoint does not provide.
+pub const METHOD_NOT_FOUND_CODE: i64 = -32601;
+/// The JSON-RPC error code for an internal error, which some nodes return intermittently under
+/// load.
+pub const INTERNAL_ERROR_CODE: i64 = -32603;
+
+impl Eth1Error {
+    /// Returns `true` if the error is likely to be temporary, such that an identical request
+    /// may succeed if retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Eth1Error::Connection(_) | Eth1Error::Timeout => true,
+            // Too many requests, bad gateway, service unavailable and gateway timeout.
+            Eth1Error::HttpStatus(status) => matches!(status, 429 | 502 | 503 | 504),
+            // Internal errors are often caused by load, whereas other codes (e.g., invalid params)
+            // fail the same way every time.
+            Eth1Error::Rpc { code, .. } => *code == INTERNAL_ERROR_CODE,
+            Eth1Error::Request(_)
+            | Eth1Error::InvalidResponse(_)
+            | Eth1Error::InvalidRequest(_)
+            | Eth1Error::PendingBlock
+            | Eth1Error::StaleHead { .. }
+            | Eth1Error::ResponseTooLarge { .. } => false,
+        }
+    }
+
+    /// Returns `true` if the endpoint does not provide the requested method, e.g., because it
+    /// is a proxy which only allows some methods.
+    pub fn is_method_not_found(&self) -> bool {
+        matches!(self, Eth1Error::Rpc { code, .. } if *code == METHOD_NOT_FOUND_CODE)
+    }
+
+    /// Classifies an error from `reqwest`.
+    ///
+    /// DNS resolution failures are reported by `reqwest` as connection errors. Timeouts are
+    /// checked first, since a connect which times out is both.
+    #[cfg(feature = "http")]
+    pub(crate) fn from_reqwest(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Eth1Error::Timeout
+        } else if error.is_connect() {
+            Eth1Error::Connection(format!("{:?}", error))
+        } else {
+            Eth1Error::Request(format!("{:?}", error))
+        }
+    }
+
+    /// Parses the `error` object of a JSON-RPC response.
+    #[cfg(feature = "http")]
+    pub(crate) fn from_rpc_error(error: &Value) -> Self {
+        Eth1Error::Rpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Eth1Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Eth1Error::Connection(e) => write!(f, "Failed to connect: {}", e),
+            Eth1Error::Request(e) => write!(f, "Request failed: {}", e),
+            Eth1Error::HttpStatus(status) => {
+                write!(f, "Response HTTP status was not 200 OK: {}", status)
+            }
+            Eth1Error::Rpc { code, message } => {
+                write!(f, "Eth1 node returned error {}: {}", code, message)
+            }
+            Eth1Error::InvalidResponse(e) => write!(f, "Invalid response: {}", e),
+            Eth1Error::InvalidRequest(e) => write!(f, "Invalid request: {}", e),
+            Eth1Error::PendingBlock => write!(f, "Block is pending"),
+            Eth1Error::Timeout => write!(f, "Request timed out"),
+            Eth1Error::StaleHead { block_number, age } => write!(
+                f,
+                "Head block {} is {}s old, the node may be stuck",
+                block_number,
+                age.as_secs()
+            ),
+            Eth1Error::ResponseTooLarge { limit } => write!(
+                f,
+                "Response body exceeded {} bytes, try requesting a smaller block range",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Eth1Error {}
+
+/// Decoding errors are reported as strings throughout the crate.
+impl From<String> for Eth1Error {
+    fn from(e: String) -> Self {
+        Eth1Error::InvalidResponse(e)
+    }
+}
+
+impl From<&str> for Eth1Error {
+    fn from(e: &str) -> Self {
+        Eth1Error::InvalidResponse(e.to_string())
+    }
+}
+
+/// An error from a consistency check of the deposit contract's data, e.g., when building the
+/// deposit tree.
+///
+/// Distinguishes data which is inconsistent from a failure to fetch it (`Transport`), since the
+/// former will not be fixed by retrying.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CheckError {
+    /// A deposit count differs from the number of deposits seen.
+    CountMismatch { expected: u64, actual: u64 },
+    /// A deposit root differs from the root computed from the deposits seen.
+    RootMismatch { expected: Hash256, actual: Hash256 },
+    /// The deposit with index `expected` was missing; the next deposit seen was `found`.
+    DepositGap { expected: u64, found: u64 },
+    /// The deposit with the given index has a signature which does not verify.
+    InvalidSignature { index: u64 },
+    /// The deposit with the given index has an amount, in gwei, below the `min` which the
+    /// contract accepts.
+    InvalidAmount { index: u64, amount: u64, min: u64 },
+    /// The data could not be fetched from the endpoint.
+    Transport(Eth1Error),
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckError::CountMismatch { expected, actual } => write!(
+                f,
+                "Deposit count mismatch: expected {}, got {}",
+                expected, actual
+            ),
+            CheckError::RootMismatch { expected, actual } => write!(
+                f,
+                "Deposit root mismatch: expected {:?}, got {:?}",
+                expected, actual
+            ),
+            CheckError::DepositGap { expected, found } => write!(
+                f,
+                "Missing deposit {}, next deposit was {}",
+                expected, found
+            ),
+            CheckError::InvalidSignature { index } => {
+                write!(f, "Deposit {} has an invalid signature", index)
+            }
+            CheckError::InvalidAmount { index, amount, min } => write!(
+                f,
+                "Deposit {} has an amount of {} gwei, below the minimum of {} gwei",
+                index, amount, min
+            ),
+            CheckError::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckError::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Eth1Error> for CheckError {
+    fn from(e: Eth1Error) -> Self {
+        CheckError::Transport(e)
+    }
+}
+
+/// An error from `verify_deposit_file`.
+///
+/// Distinguishes a file which could not be read or parsed from a failure to fetch the deposits
+/// (`Transport`).
+#[derive(Debug)]
+pub enum VerifyFileError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file is not a valid `deposit_data-*.json` file.
+    InvalidFile(String),
+    /// The deposits could not be fetched from the endpoint.
+    Transport(Eth1Error),
+}
+
+impl fmt::Display for VerifyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyFileError::Io(e) => write!(f, "Failed to read deposit data file: {}", e),
+            VerifyFileError::InvalidFile(e) => write!(f, "Invalid deposit data file: {}", e),
+            VerifyFileError::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifyFileError::Io(e) => Some(e),
+            VerifyFileError::InvalidFile(_) => None,
+            VerifyFileError::Transport(e) => Some(e),
+        }
+    }
+}
+
+impl From<Eth1Error> for VerifyFileError {
+    fn from(e: Eth1Error) -> Self {
+        VerifyFileError::Transport(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_errors_and_timeouts_are_transient() {
+        assert!(Eth1Error::Connection("refused".to_string()).is_transient());
+        assert!(Eth1Error::Timeout.is_transient());
+    }
+
+    #[test]
+    fn overload_and_gateway_statuses_are_transient() {
+        for status in [429, 502, 503, 504] {
+            assert!(Eth1Error::HttpStatus(status).is_transient(), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 500] {
+            assert!(!Eth1Error::HttpStatus(status).is_transient(), "{}", status);
+        }
+    }
+
+    #[test]
+    fn only_internal_rpc_errors_are_transient() {
+        let rpc = |code| Eth1Error::Rpc {
+            code,
+            message: "error".to_string(),
+        };
+        assert!(rpc(INTERNAL_ERROR_CODE).is_transient());
+        assert!(!rpc(METHOD_NOT_FOUND_CODE).is_transient());
+        assert!(!rpc(-32602).is_transient());
+        assert!(!rpc(-32000).is_transient());
+    }
+
+    #[test]
+    fn other_errors_are_not_transient() {
+        assert!(!Eth1Error::Request("builder error".to_string()).is_transient());
+        assert!(!Eth1Error::InvalidResponse("no result".to_string()).is_transient());
+        assert!(!Eth1Error::InvalidRequest("too large".to_string()).is_transient());
+        assert!(!Eth1Error::PendingBlock.is_transient());
+        assert!(!Eth1Error::ResponseTooLarge { limit: 1024 }.is_transient());
+        assert!(!Eth1Error::StaleHead {
+            block_number: 1,
+            age: Duration::from_secs(600),
+        }
+        .is_transient());
+    }
+}
@@ -0,0 +1,74 @@
+//! The consensus-spec `Eth1Data` container.
+
+use crate::tree_hash::{merkleize, u64_chunk};
+use ethereum_types::H256 as Hash256;
+
+/// The consensus-spec `Eth1Data` container, i.e., the values beacon nodes vote on.
+///
+/// All three values must be read at the same eth1 block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Eth1Data {
+    pub deposit_root: Hash256,
+    pub deposit_count: u64,
+    pub block_hash: Hash256,
+}
+
+impl Eth1Data {
+    /// Returns the SSZ hash tree root of `self`.
+    pub fn tree_hash_root(&self) -> Hash256 {
+        merkleize(&[
+            self.deposit_root.to_fixed_bytes(),
+            u64_chunk(self.deposit_count),
+            self.block_hash.to_fixed_bytes(),
+        ])
+    }
+}
+
+/// Number of bytes in the SSZ encoding of `Eth1Data`.
+#[cfg(feature = "ssz")]
+const ETH1_DATA_SSZ_LEN: usize = 32 + 8 + 32;
+
+#[cfg(feature = "ssz")]
+impl ssz::Encode for Eth1Data {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        ETH1_DATA_SSZ_LEN
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.deposit_root.as_bytes());
+        buf.extend_from_slice(&self.deposit_count.to_le_bytes());
+        buf.extend_from_slice(self.block_hash.as_bytes());
+    }
+}
+
+#[cfg(feature = "ssz")]
+impl ssz::Decode for Eth1Data {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        ETH1_DATA_SSZ_LEN
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, ssz::DecodeError> {
+        if bytes.len() != ETH1_DATA_SSZ_LEN {
+            return Err(ssz::DecodeError::InvalidByteLength {
+                len: bytes.len(),
+                expected: ETH1_DATA_SSZ_LEN,
+            });
+        }
+
+        let mut deposit_count = [0; 8];
+        deposit_count.copy_from_slice(&bytes[32..40]);
+        Ok(Self {
+            deposit_root: Hash256::from_slice(&bytes[0..32]),
+            deposit_count: u64::from_le_bytes(deposit_count),
+            block_hash: Hash256::from_slice(&bytes[40..72]),
+        })
+    }
+}
@@ -0,0 +1,363 @@
+//! Exporting deposits in the `deposit_data-*.json` format written by the staking deposit CLI,
+//! which beacon tooling and launchpads import.
+
+use crate::{
+    fork_version, DepositData, DepositLog, Eth1Id, PublicKey, Signature, WithdrawalCredentials,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// One entry of a `deposit_data-*.json` file. Byte fields are hex without a `0x` prefix, as
+/// written by the deposit CLI.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DepositDataJson {
+    pub pubkey: String,
+    pub withdrawal_credentials: String,
+    /// The deposit amount, in gwei.
+    pub amount: u64,
+    pub signature: String,
+    pub deposit_message_root: String,
+    pub deposit_data_root: String,
+    pub fork_version: String,
+    pub network_name: String,
+}
+
+impl DepositDataJson {
+    /// Returns the entry for `deposit`, made on the network with the given genesis
+    /// `fork_version` and `network_name` (e.g., `mainnet`).
+    pub fn new(deposit: &DepositLog, fork_version: [u8; 4], network_name: &str) -> Self {
+        let data = DepositData::from(deposit);
+        Self {
+            pubkey: hex::encode(deposit.pubkey.as_bytes()),
+            withdrawal_credentials: hex::encode(deposit.withdrawal_credentials.as_bytes()),
+            amount: deposit.amount,
+            signature: hex::encode(deposit.signature.as_bytes()),
+            deposit_message_root: hex::encode(data.deposit_message_root()),
+            deposit_data_root: hex::encode(data.tree_hash_root()),
+            fork_version: hex::encode(fork_version),
+            network_name: network_name.to_string(),
+        }
+    }
+
+    /// Returns the `DepositData` of this entry, e.g., to submit it to a deposit contract.
+    ///
+    /// Returns an error if a field is not hex of the right length, or if the
+    /// `deposit_data_root` is not the root of the other fields, since the contract would reject
+    /// the deposit.
+    pub fn to_deposit_data(&self) -> Result<DepositData, String> {
+        let data = DepositData {
+            pubkey: PublicKey::from_slice(&decode_hex("pubkey", &self.pubkey)?)?.0,
+            withdrawal_credentials: WithdrawalCredentials::from_slice(&decode_hex(
+                "withdrawal_credentials",
+                &self.withdrawal_credentials,
+            )?)?
+            .0
+            .into(),
+            amount: self.amount,
+            signature: Signature::from_slice(&decode_hex("signature", &self.signature)?)?.0,
+        };
+        let root = hex::encode(data.tree_hash_root());
+        if root.eq_ignore_ascii_case(&self.deposit_data_root) {
+            Ok(data)
+        } else {
+            Err(format!(
+                "Deposit data root of {} was {}, expected {}",
+                self.pubkey, self.deposit_data_root, root
+            ))
+        }
+    }
+}
+
+/// Parses the hex `value` of the field `name`, written without a `0x` prefix.
+fn decode_hex(name: &str, value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|e| format!("Invalid {} {}: {:?}", name, value, e))
+}
+
+/// A field which differs between an entry of a deposit data file and a deposit made on chain.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldMismatch {
+    /// The name of the field in the deposit data file, e.g., `withdrawal_credentials`.
+    pub field: &'static str,
+    /// The value in the deposit data file.
+    pub expected: String,
+    /// The value of the deposit on chain.
+    pub on_chain: String,
+}
+
+impl DepositDataJson {
+    /// Returns each field which differs between this entry and the on-chain `deposit`, of
+    /// `pubkey`, `withdrawal_credentials`, `amount`, `signature` and `deposit_data_root`. Hex
+    /// is compared case-insensitively.
+    pub fn mismatches(&self, deposit: &DepositLog) -> Vec<FieldMismatch> {
+        let on_chain = Self::new(deposit, [0; 4], "");
+        let expected_amount = self.amount.to_string();
+        let on_chain_amount = on_chain.amount.to_string();
+        let fields = [
+            ("pubkey", &self.pubkey, &on_chain.pubkey),
+            (
+                "withdrawal_credentials",
+                &self.withdrawal_credentials,
+                &on_chain.withdrawal_credentials,
+            ),
+            ("amount", &expected_amount, &on_chain_amount),
+            ("signature", &self.signature, &on_chain.signature),
+            (
+                "deposit_data_root",
+                &self.deposit_data_root,
+                &on_chain.deposit_data_root,
+            ),
+        ];
+        fields
+            .iter()
+            .filter(|(_, expected, actual)| !expected.eq_ignore_ascii_case(actual))
+            .map(|(field, expected, actual)| FieldMismatch {
+                field,
+                expected: expected.to_string(),
+                on_chain: actual.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Parses the JSON contents of a `deposit_data-*.json` file.
+pub fn parse_deposit_data(json: &str) -> Result<Vec<DepositDataJson>, String> {
+    serde_json::from_str(json).map_err(|e| format!("Failed to parse deposit data: {}", e))
+}
+
+/// Returns `deposits` as the JSON contents of a `deposit_data-*.json` file.
+pub fn export_deposit_data(
+    deposits: &[DepositLog],
+    fork_version: [u8; 4],
+    network_name: &str,
+) -> Result<String, String> {
+    let entries: Vec<_> = deposits
+        .iter()
+        .map(|deposit| DepositDataJson::new(deposit, fork_version, network_name))
+        .collect();
+    serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize deposit data: {}", e))
+}
+
+/// As per `export_deposit_data`, but with the `fork_version` and `network_name` of a known
+/// `network`.
+///
+/// Returns an error for a custom network, which has neither.
+pub fn export_deposit_data_for_network(
+    deposits: &[DepositLog],
+    network: &Eth1Id,
+) -> Result<String, String> {
+    match (fork_version(network.clone()), network.chain_name()) {
+        (Some(fork_version), Some(network_name)) => {
+            export_deposit_data(deposits, fork_version, network_name)
+        }
+        _ => Err(format!("No fork version is known for {:?}", network)),
+    }
+}
+
+/// The header row written by `DepositCsvWriter`.
+const CSV_HEADER: &str =
+    "index,block_number,transaction_hash,pubkey,withdrawal_credentials,amount,signature";
+
+/// Writes deposits as CSV one row at a time, so that an export of the full history need not hold
+/// every deposit in memory.
+///
+/// Byte fields are `0x`-prefixed hex and the amount is in gwei, so no field needs quoting.
+pub struct DepositCsvWriter<W: Write> {
+    writer: W,
+    rows: u64,
+}
+
+impl<W: Write> DepositCsvWriter<W> {
+    /// Writes the header row to `writer`.
+    pub fn new(mut writer: W) -> Result<Self, String> {
+        writeln!(writer, "{}", CSV_HEADER)
+            .map_err(|e| format!("Failed to write CSV header: {}", e))?;
+        Ok(Self { writer, rows: 0 })
+    }
+
+    /// Writes a row for `deposit`.
+    pub fn write(&mut self, deposit: &DepositLog) -> Result<(), String> {
+        writeln!(
+            self.writer,
+            "{},{},{:?},{},{},{},{}",
+            deposit.index,
+            deposit.block_number,
+            deposit.transaction_hash,
+            deposit.pubkey,
+            deposit.withdrawal_credentials,
+            deposit.amount,
+            deposit.signature
+        )
+        .map_err(|e| {
+            format!(
+                "Failed to write CSV row for deposit {}: {}",
+                deposit.index, e
+            )
+        })?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Returns the number of rows written, excluding the header.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush CSV: {}", e))?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes deposits as JSON Lines (NDJSON), one `DepositLog` object per line, for piping into
+/// tools which process a stream of records rather than a single array.
+pub struct DepositNdjsonWriter<W: Write> {
+    writer: W,
+    rows: u64,
+}
+
+impl<W: Write> DepositNdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, rows: 0 }
+    }
+
+    /// Writes a line for `deposit`.
+    pub fn write(&mut self, deposit: &DepositLog) -> Result<(), String> {
+        serde_json::to_writer(&mut self.writer, deposit)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writeln!(self.writer).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to write deposit {}: {}", deposit.index, e))?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Returns the number of lines written.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush NDJSON: {}", e))?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{deposit, DEPOSIT_DATA_FILE, DEPOSIT_DATA_ROOT};
+
+    #[test]
+    fn parses_a_deposit_data_file() {
+        let entries = parse_deposit_data(DEPOSIT_DATA_FILE).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].amount, 32_000_000_000);
+        assert_eq!(entries[0].network_name, "mainnet");
+
+        let data = entries[0].to_deposit_data().unwrap();
+        assert_eq!(hex::encode(data.tree_hash_root()), DEPOSIT_DATA_ROOT);
+        assert_eq!(hex::encode(data.pubkey), entries[0].pubkey);
+    }
+
+    #[test]
+    fn rejects_a_deposit_data_root_which_does_not_match() {
+        let mut entry = parse_deposit_data(DEPOSIT_DATA_FILE).unwrap().remove(0);
+        entry.amount = 1_000_000_000;
+        assert!(entry.to_deposit_data().is_err());
+
+        let mut entry = parse_deposit_data(DEPOSIT_DATA_FILE).unwrap().remove(0);
+        entry.deposit_data_root = "00".repeat(32);
+        assert!(entry.to_deposit_data().is_err());
+    }
+
+    #[test]
+    fn rejects_fields_of_the_wrong_length() {
+        let mut entry = parse_deposit_data(DEPOSIT_DATA_FILE).unwrap().remove(0);
+        entry.pubkey.truncate(94);
+        assert!(entry.to_deposit_data().is_err());
+    }
+
+    #[test]
+    fn exported_entries_round_trip() {
+        let deposits = vec![deposit(0, 5), deposit(1, 6)];
+        let json = export_deposit_data(&deposits, [0; 4], "mainnet").unwrap();
+        let entries = parse_deposit_data(&json).unwrap();
+        assert_eq!(entries.len(), 2);
+        for (entry, deposit) in entries.iter().zip(&deposits) {
+            assert_eq!(entry.to_deposit_data().unwrap(), DepositData::from(deposit));
+            assert_eq!(entry.fork_version, "00000000");
+        }
+    }
+
+    #[test]
+    fn mismatches_are_listed_by_field() {
+        let deposit = deposit(0, 5);
+        let mut entry = DepositDataJson::new(&deposit, [0; 4], "mainnet");
+        assert_eq!(entry.mismatches(&deposit), vec![]);
+
+        // Hex is compared case-insensitively.
+        entry.signature = entry.signature.to_uppercase();
+        assert_eq!(entry.mismatches(&deposit), vec![]);
+
+        entry.pubkey = "11".repeat(48);
+        entry.amount = 1_000_000_000;
+        let fields: Vec<_> = entry
+            .mismatches(&deposit)
+            .into_iter()
+            .map(|mismatch| mismatch.field)
+            .collect();
+        assert_eq!(fields, vec!["pubkey", "amount"]);
+        assert_eq!(
+            entry.mismatches(&deposit)[1],
+            FieldMismatch {
+                field: "amount",
+                expected: "1000000000".to_string(),
+                on_chain: "32000000000".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn writes_a_csv_header_and_rows() {
+        let deposit = deposit(3, 5);
+        let mut writer = DepositCsvWriter::new(vec![]).unwrap();
+        writer.write(&deposit).unwrap();
+        assert_eq!(writer.rows(), 1);
+
+        let csv = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "3,5,0x{:064x},0x{},0x{},32000000000,0x{}",
+                3,
+                "aa".repeat(48),
+                "bb".repeat(32),
+                "cc".repeat(96)
+            )
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn writes_one_json_object_per_line() {
+        let mut writer = DepositNdjsonWriter::new(vec![]);
+        writer.write(&deposit(0, 5)).unwrap();
+        writer.write(&deposit(1, 6)).unwrap();
+        assert_eq!(writer.rows(), 2);
+
+        let ndjson = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let indices: Vec<_> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["index"].clone())
+            .collect();
+        assert_eq!(indices, vec![0, 1]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Following the deposit contract with an installed log filter, i.e., `eth_newFilter` and
+//! `eth_getFilterChanges`, as an HTTP-only alternative to a WebSocket subscription.
+
+use crate::client::{block_filter_param, response_result};
+use crate::{decode_deposit_log_with_topic, BlockQuery, DepositLog, Eth1Client, Eth1Error, Log};
+use serde_json::json;
+
+impl Eth1Client {
+    /// Installs a filter on the endpoint for the `Self::deposit_topic` logs of `address`, from
+    /// `from_block` onwards, returning the id of the filter.
+    pub async fn new_deposit_filter(
+        &self,
+        address: &str,
+        from_block: BlockQuery,
+    ) -> Result<String, Eth1Error> {
+        let params = json! ([{
+            "address": address,
+            "topics": [self.deposit_topic()],
+            "fromBlock": block_filter_param(from_block)?,
+        }]);
+
+        let response_body = self.send_rpc_request("eth_newFilter", params).await?;
+        Ok(response_result(&response_body)?
+            .ok_or("No result was returned for new filter")?
+            .as_str()
+            .ok_or("Filter id was not string")?
+            .to_string())
+    }
+
+    /// Returns all logs matching the filter with the given `id`.
+    pub async fn get_filter_logs(&self, id: &str) -> Result<Vec<Log>, Eth1Error> {
+        let response_body = self
+            .send_rpc_request_bytes("eth_getFilterLogs", json!([id]))
+            .await?;
+        self.decode_logs(&response_body)
+            .map_err(|e| Eth1Error::InvalidResponse(format!("Failed to get filter logs: {}", e)))
+    }
+
+    /// Returns the logs matching the filter with the given `id` since it was last polled.
+    pub async fn get_filter_changes(&self, id: &str) -> Result<Vec<Log>, Eth1Error> {
+        let response_body = self
+            .send_rpc_request_bytes("eth_getFilterChanges", json!([id]))
+            .await?;
+        self.decode_logs(&response_body)
+            .map_err(|e| Eth1Error::InvalidResponse(format!("Failed to get filter changes: {}", e)))
+    }
+
+    /// Removes the filter with the given `id`, returning `false` if it did not exist.
+    pub async fn uninstall_filter(&self, id: &str) -> Result<bool, Eth1Error> {
+        let response_body = self
+            .send_rpc_request("eth_uninstallFilter", json!([id]))
+            .await?;
+        Ok(response_result(&response_body)?
+            .ok_or("No result was returned for uninstall filter")?
+            .as_bool()
+            .ok_or("Uninstall filter result was not bool")?)
+    }
+}
+
+/// A change to the deposits seen by a `DepositFilter`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DepositEvent {
+    /// A new deposit.
+    Added(DepositLog),
+    /// A deposit which was previously `Added`, but whose block has been re-orged out. All
+    /// deposits with a higher index are also rolled back, and will be added again once they
+    /// are included in the new chain.
+    Removed(DepositLog),
+}
+
+/// Polls an installed filter for new deposits.
+///
+/// Nodes drop filters which have not been polled for a while (five minutes for geth). When that
+/// happens the filter is reinstalled from the last block a deposit was seen in, and any
+/// deposits which were already returned are skipped, so no deposit is missed or repeated.
+#[derive(Debug, Clone)]
+pub struct DepositFilter {
+    address: String,
+    id: Option<String>,
+    from_block: u64,
+    deposit_count: u64,
+}
+
+impl DepositFilter {
+    /// Returns a filter for the deposits of `address` from `from_block` onwards. It is
+    /// installed on the first call to `Self::poll`.
+    pub fn new(address: &str, from_block: u64) -> Self {
+        Self {
+            address: address.to_string(),
+            id: None,
+            from_block,
+            deposit_count: 0,
+        }
+    }
+
+    /// Returns the changes to the deposits since the last poll: any removals due to a re-org,
+    /// followed by new deposits ordered by deposit index.
+    ///
+    /// The first poll returns all deposits from the `from_block` given to `Self::new`.
+    pub async fn poll(&mut self, client: &Eth1Client) -> Result<Vec<DepositEvent>, Eth1Error> {
+        let changes = match &self.id {
+            Some(id) => match client.get_filter_changes(id).await {
+                Err(e) if is_filter_not_found(&e) => None,
+                result => Some(result?),
+            },
+            None => None,
+        };
+
+        let logs = match changes {
+            Some(logs) => logs,
+            None => {
+                let id = client
+                    .new_deposit_filter(&self.address, BlockQuery::Number(self.from_block))
+                    .await?;
+                self.id = Some(id.clone());
+                client.get_filter_logs(&id).await?
+            }
+        };
+
+        let (removed, mut added): (Vec<_>, Vec<_>) = logs
+            .iter()
+            .map(|log| decode_deposit_log_with_topic(log, client.deposit_topic()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .partition(|deposit| deposit.removed);
+
+        let mut events = vec![];
+        let seen = self.deposit_count;
+        for deposit in removed {
+            if deposit.index < seen {
+                self.deposit_count = std::cmp::min(self.deposit_count, deposit.index);
+                self.from_block = std::cmp::min(self.from_block, deposit.block_number);
+                events.push(DepositEvent::Removed(deposit));
+            }
+        }
+
+        added.sort_by_key(|deposit| deposit.index);
+        added.retain(|deposit| deposit.index >= self.deposit_count);
+        added.dedup_by_key(|deposit| deposit.index);
+        if let Some(last) = added.last() {
+            self.deposit_count = last.index + 1;
+            self.from_block = last.block_number;
+        }
+        events.extend(added.into_iter().map(DepositEvent::Added));
+
+        Ok(events)
+    }
+}
+
+/// Returns `true` if `error` indicates that the node no longer knows of the filter.
+fn is_filter_not_found(error: &Eth1Error) -> bool {
+    match error {
+        Eth1Error::Rpc { message, .. } => message.to_lowercase().contains("filter not found"),
+        _ => false,
+    }
+}
@@ -0,0 +1,96 @@
+//! Readiness diagnostics for an eth1 endpoint.
+
+use crate::{BlockQuery, Eth1Client, Eth1Error, Eth1Id, SyncStatus};
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The outcome of a single diagnostic request.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CheckResult<T> {
+    /// Time taken for the request to complete (or fail).
+    pub latency: Duration,
+    pub result: Result<T, Eth1Error>,
+}
+
+impl<T> CheckResult<T> {
+    /// Runs `future`, recording how long it took.
+    async fn measure<F: Future<Output = Result<T, Eth1Error>>>(future: F) -> Self {
+        let start = Instant::now();
+        let result = future.await;
+        Self {
+            latency: start.elapsed(),
+            result,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// The results of running each diagnostic in `Eth1Client::health_check`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct HealthReport {
+    pub block_number: CheckResult<u64>,
+    pub chain_id: CheckResult<Eth1Id>,
+    pub syncing: CheckResult<SyncStatus>,
+    /// The deposit count at the latest block, `None` if the contract was not found.
+    pub deposit_count: CheckResult<Option<u64>>,
+    /// The widest `eth_getLogs` range allowed by the endpoint, if already found by
+    /// `Eth1Client::detect_max_block_range`. The health check does not probe for it.
+    pub max_block_range: Option<u64>,
+}
+
+impl HealthReport {
+    /// Returns `true` if every request succeeded, the node is synced and the deposit contract
+    /// exists.
+    pub fn is_healthy(&self) -> bool {
+        self.block_number.is_ok()
+            && self.chain_id.is_ok()
+            && self.syncing.result == Ok(SyncStatus::Synced)
+            && matches!(self.deposit_count.result, Ok(Some(_)))
+    }
+}
+
+impl Eth1Client {
+    /// Runs `eth_blockNumber`, `eth_chainId`, `eth_syncing` and a `get_deposit_count()` call to
+    /// the `deposit_contract`, recording the latency and result of each.
+    ///
+    /// The requests are made sequentially so that latencies are not skewed by each other.
+    pub async fn health_check(&self, deposit_contract: &str) -> HealthReport {
+        HealthReport {
+            block_number: CheckResult::measure(self.get_block_number()).await,
+            chain_id: CheckResult::measure(self.get_chain_id()).await,
+            syncing: CheckResult::measure(self.get_syncing()).await,
+            deposit_count: CheckResult::measure(
+                self.get_deposit_count(deposit_contract, BlockQuery::Latest),
+            )
+            .await,
+            max_block_range: self.detected_max_block_range(),
+        }
+    }
+
+    /// Returns how far the timestamp of the head block is behind the system clock.
+    ///
+    /// Returns `Eth1Error::StaleHead` if it is more than `max_age` behind, which suggests that
+    /// the node has stopped following the chain, or the chain itself has stopped. Blocks are
+    /// normally seconds apart, so a `max_age` of a few minutes allows for missed slots.
+    pub async fn staleness_check(&self, max_age: Duration) -> Result<Duration, Eth1Error> {
+        let head = self.get_block(BlockQuery::Latest).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let age = now
+            .checked_sub(Duration::from_secs(head.timestamp))
+            .unwrap_or_default();
+
+        if age > max_age {
+            Err(Eth1Error::StaleHead {
+                block_number: head.number,
+                age,
+            })
+        } else {
+            Ok(age)
+        }
+    }
+}
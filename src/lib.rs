@@ -3,21 +3,152 @@
 //!
 //! All remote functions return a future (i.e., are async).
 //!
+//! Requests can be made through an `Eth1Client`, which reuses a single HTTP client, or through the
+//! free functions, which create a new one for each request.
+//!
 //! Does not use a web3 library, instead it uses `reqwest` (`hyper`) to call the remote endpoint
 //! and `serde` to decode the response.
 //!
+//! The decoding functions (e.g., `decode_logs_response`, `decode_deposit_log` and the tree hash
+//! roots) are pure and do not touch the HTTP client, so they can be used on logs obtained
+//! elsewhere.
+//! Everything which talks to an endpoint is behind the default `http` feature; disabling it
+//! drops `reqwest`, `tokio` and `futures` from the dependency tree.
+//!
 //! ## Note
 //!
 //! There is no ABI parsing here, all function signatures and topics are hard-coded as constants.
 
-use ethereum_types::H256 as Hash256;
-use futures::future::TryFutureExt;
-use reqwest::{header::CONTENT_TYPE, ClientBuilder, StatusCode};
+use ethereum_types::{H160, H256 as Hash256};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::ops::Range;
+#[cfg(feature = "http")]
+use serde_json::Value;
+use std::fmt;
 use std::str::FromStr;
-use std::time::Duration;
+
+mod abi;
+#[cfg(feature = "http")]
+mod adaptive;
+#[cfg(feature = "http")]
+mod backpressure;
+#[cfg(feature = "http")]
+mod body;
+#[cfg(feature = "http")]
+mod cache;
+mod calldata;
+mod chunk;
+#[cfg(feature = "http")]
+mod client;
+#[cfg(feature = "http")]
+mod concurrent;
+#[cfg(feature = "http")]
+mod confirmations;
+#[cfg(feature = "http")]
+mod count;
+mod decode;
+#[cfg(feature = "http")]
+mod deployment;
+mod deposit_log;
+mod deposit_tree;
+mod error;
+mod eth1_data;
+mod export;
+#[cfg(feature = "http")]
+mod filter;
+#[cfg(feature = "http")]
+mod health;
+#[cfg(feature = "http")]
+mod limits;
+mod logs;
+#[cfg(feature = "http")]
+mod multicall;
+#[cfg(feature = "http")]
+mod replay;
+#[cfg(feature = "http")]
+mod search;
+#[cfg(feature = "tower")]
+mod service;
+#[cfg(feature = "http")]
+mod snapshot;
+#[cfg(feature = "http")]
+mod stream;
+mod sync_state;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "http")]
+mod timestamp;
+#[cfg(feature = "http")]
+mod transaction;
+mod tree_hash;
+mod types;
+#[cfg(feature = "http")]
+mod verify;
+#[cfg(feature = "http")]
+mod voting;
+
+#[cfg(feature = "http")]
+pub use adaptive::{get_deposit_logs_adaptive, is_response_too_large};
+pub use calldata::{deposit_calldata, deposit_value_wei};
+pub use chunk::{chunk_range, estimate_request_count};
+#[cfg(feature = "http")]
+pub use client::{
+    get_block, get_block_number, get_chain_id, get_deposit_count, get_deposit_logs_for_addresses,
+    get_deposit_logs_in_range, get_deposit_root, get_deposits_in_range, get_network_id,
+    get_root_and_count, get_syncing, send_rpc_request, ClientConfig, Eth1Client, NetworkSource,
+    RetryConfig, RetryPredicate,
+};
+#[cfg(feature = "http")]
+pub use concurrent::{get_deposit_chunks_concurrently, get_deposits_concurrently};
+#[cfg(feature = "http")]
+pub use confirmations::get_confirmed_deposits;
+#[cfg(feature = "http")]
+pub use count::{
+    check_range_available, count_deposits_between, count_deposits_in_range, deposit_count_history,
+    fetch_new_deposits, last_deposit_block, RangeDepositCount,
+};
+#[cfg(feature = "http")]
+pub use deployment::find_deployment_block;
+pub use deposit_log::{
+    decode_deposit_log, decode_deposit_log_with_topic, group_by_block, DepositConfig, DepositData,
+    DepositLog, MAX_EFFECTIVE_BALANCE, MAX_EFFECTIVE_BALANCE_ELECTRA, MIN_DEPOSIT_AMOUNT,
+};
+pub use deposit_tree::{DepositTree, DEPOSIT_CONTRACT_TREE_DEPTH};
+pub use error::{
+    CheckError, Eth1Error, VerifyFileError, INTERNAL_ERROR_CODE, METHOD_NOT_FOUND_CODE,
+};
+pub use eth1_data::Eth1Data;
+pub use export::{
+    export_deposit_data, export_deposit_data_for_network, parse_deposit_data, DepositCsvWriter,
+    DepositDataJson, DepositNdjsonWriter, FieldMismatch,
+};
+#[cfg(feature = "http")]
+pub use filter::{DepositEvent, DepositFilter};
+#[cfg(feature = "http")]
+pub use health::{CheckResult, HealthReport};
+pub use logs::{decode_logs_response, decode_logs_response_lenient};
+#[cfg(feature = "http")]
+pub use multicall::{MULTICALL3_ADDRESS, MULTICALL_AGGREGATE_FN_SIGNATURE};
+#[cfg(feature = "http")]
+pub use replay::{deposit_transaction, submit_deposits};
+#[cfg(feature = "http")]
+pub use search::find_deposits_by_pubkey;
+#[cfg(feature = "tower")]
+pub use service::{HttpTransport, RpcRequest};
+#[cfg(feature = "http")]
+pub use snapshot::{snapshot, DepositSnapshot};
+pub use sync_state::SyncState;
+#[cfg(feature = "http")]
+pub use timestamp::{find_block_by_timestamp, get_deposits_with_timestamps};
+#[cfg(feature = "http")]
+pub use transaction::{get_transaction_by_hash, Transaction};
+pub use types::{
+    PublicKey, Signature, WithdrawalCredentials, BLS_WITHDRAWAL_PREFIX,
+    COMPOUNDING_WITHDRAWAL_PREFIX, ETH1_ADDRESS_WITHDRAWAL_PREFIX,
+};
+#[cfg(feature = "http")]
+pub use verify::{verify_deposit_file, DepositVerification, EntryVerification, VerifyReport};
+#[cfg(feature = "http")]
+pub use voting::get_voting_block_range;
 
 /// `keccak("DepositEvent(bytes,bytes,bytes,bytes,bytes)")`
 pub const DEPOSIT_EVENT_TOPIC: &str =
@@ -26,32 +157,72 @@ pub const DEPOSIT_EVENT_TOPIC: &str =
 pub const DEPOSIT_ROOT_FN_SIGNATURE: &str = "0xc5f2892f";
 /// `keccak("get_deposit_count()")[0..4]`
 pub const DEPOSIT_COUNT_FN_SIGNATURE: &str = "0x621fd130";
+/// `keccak("deposit(bytes,bytes,bytes,bytes32)")[0..4]`
+pub const DEPOSIT_FN_SIGNATURE: &str = "0x22895118";
 
 /// Number of bytes in deposit contract deposit root response.
 pub const DEPOSIT_COUNT_RESPONSE_BYTES: usize = 96;
 /// Number of bytes in deposit contract deposit root (value only).
 pub const DEPOSIT_ROOT_BYTES: usize = 32;
 
-/// Represents an eth1 chain/network id.
+/// Represents an eth1 chain id, as returned by `eth_chainId`.
+///
+/// The network id returned by `net_version` is a distinct value, see `NetworkId`.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Eth1Id {
     Goerli,
     Mainnet,
+    Sepolia,
+    Holesky,
     Custom(u64),
 }
 
+impl Eth1Id {
+    /// Returns the canonical lower-case name of a known network (e.g., `mainnet`), as used in
+    /// the `network_name` of a `deposit_data-*.json` file, or `None` for a custom network.
+    pub fn chain_name(&self) -> Option<&'static str> {
+        match self {
+            Eth1Id::Mainnet => Some("mainnet"),
+            Eth1Id::Goerli => Some("goerli"),
+            Eth1Id::Sepolia => Some("sepolia"),
+            Eth1Id::Holesky => Some("holesky"),
+            Eth1Id::Custom(_) => None,
+        }
+    }
+}
+
+/// Returns the genesis fork version of the beacon chain of a known network, which is part of the
+/// domain that deposit signatures are made over, or `None` for a custom network.
+pub fn fork_version(id: Eth1Id) -> Option<[u8; 4]> {
+    match id {
+        Eth1Id::Mainnet => Some([0x00, 0x00, 0x00, 0x00]),
+        Eth1Id::Goerli => Some([0x00, 0x00, 0x10, 0x20]),
+        Eth1Id::Sepolia => Some([0x90, 0x00, 0x00, 0x69]),
+        Eth1Id::Holesky => Some([0x01, 0x01, 0x70, 0x00]),
+        Eth1Id::Custom(_) => None,
+    }
+}
+
 /// Used to identify a block when querying the Eth1 node.
 #[derive(Clone, Copy)]
 pub enum BlockQuery {
     Number(u64),
+    /// The block with the given hash, which identifies a block even across a re-org.
+    Hash(Hash256),
     Latest,
+    /// The most recent block which is unlikely to be re-orged, post-merge.
+    Safe,
+    /// The most recent finalized block, post-merge.
+    Finalized,
 }
 
-impl Into<u64> for Eth1Id {
-    fn into(self) -> u64 {
-        match self {
+impl From<Eth1Id> for u64 {
+    fn from(id: Eth1Id) -> u64 {
+        match id {
             Eth1Id::Mainnet => 1,
             Eth1Id::Goerli => 5,
+            Eth1Id::Sepolia => 11155111,
+            Eth1Id::Holesky => 17000,
             Eth1Id::Custom(id) => id,
         }
     }
@@ -63,6 +234,8 @@ impl From<u64> for Eth1Id {
         match id {
             id if id == into(Eth1Id::Mainnet) => Eth1Id::Mainnet,
             id if id == into(Eth1Id::Goerli) => Eth1Id::Goerli,
+            id if id == into(Eth1Id::Sepolia) => Eth1Id::Sepolia,
+            id if id == into(Eth1Id::Holesky) => Eth1Id::Holesky,
             id => Eth1Id::Custom(id),
         }
     }
@@ -72,181 +245,204 @@ impl FromStr for Eth1Id {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        u64::from_str_radix(s, 10)
+        s.parse::<u64>()
             .map(Into::into)
             .map_err(|e| format!("Failed to parse eth1 network id {}", e))
     }
 }
 
-/// Get the eth1 network id of the given endpoint.
-pub async fn get_network_id(endpoint: &str, timeout: Duration) -> Result<Eth1Id, String> {
-    let response_body = send_rpc_request(endpoint, "net_version", json!([]), timeout).await?;
-    Eth1Id::from_str(
-        response_result(&response_body)?
-            .ok_or_else(|| "No result was returned for network id".to_string())?
-            .as_str()
-            .ok_or_else(|| "Data was not string")?,
-    )
+/// An eth1 network id, as returned by `net_version`.
+///
+/// This identifies the peer-to-peer network, whereas the chain id (`Eth1Id`) is used in
+/// transaction signatures. They are equal on most networks, but not all (e.g., Ethereum Classic
+/// has network id 1 and chain id 61).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct NetworkId(pub u64);
+
+impl FromStr for NetworkId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u64>()
+            .map(NetworkId)
+            .map_err(|e| format!("Failed to parse eth1 network id {}", e))
+    }
 }
 
-/// Get the eth1 chain id of the given endpoint.
-pub async fn get_chain_id(endpoint: &str, timeout: Duration) -> Result<Eth1Id, String> {
-    let response_body = send_rpc_request(endpoint, "eth_chainId", json!([]), timeout).await?;
-    hex_to_u64_be(
-        response_result(&response_body)?
-            .ok_or_else(|| "No result was returned for chain id".to_string())?
-            .as_str()
-            .ok_or_else(|| "Data was not string")?,
-    )
-    .map(Into::into)
+impl FromStr for BlockQuery {
+    type Err = String;
+
+    /// Parses either a block tag (`latest`, `safe` or `finalized`), a `0x`-prefixed block hash
+    /// or a decimal block number.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(BlockQuery::Latest),
+            "safe" => Ok(BlockQuery::Safe),
+            "finalized" => Ok(BlockQuery::Finalized),
+            hash if hash.starts_with("0x") => decode::hex_to_hash(hash)
+                .map(BlockQuery::Hash)
+                .map_err(|e| format!("Failed to parse block hash {}: {}", hash, e)),
+            number => number
+                .parse::<u64>()
+                .map(BlockQuery::Number)
+                .map_err(|e| format!("Failed to parse block {}: {}", number, e)),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// A reduced set of fields from an Eth1 block.
+///
+/// Serializes with `hash` as a `0x`-prefixed hex string, so it can be cached or emitted as JSON.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub hash: Hash256,
     pub timestamp: u64,
     pub number: u64,
 }
 
-/// Returns the current block number.
-///
-/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
-pub async fn get_block_number(endpoint: &str, timeout: Duration) -> Result<u64, String> {
-    let response_body = send_rpc_request(endpoint, "eth_blockNumber", json!([]), timeout).await?;
-    hex_to_u64_be(
-        response_result(&response_body)?
-            .ok_or_else(|| "No result field was returned for block number".to_string())?
-            .as_str()
-            .ok_or_else(|| "Data was not string")?,
-    )
-    .map_err(|e| format!("Failed to get block number: {}", e))
+impl fmt::Display for Block {
+    /// E.g., `#11184524 0x1234…abcd ts=1604173317`, with the hash abbreviated.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {} ts={}", self.number, self.hash, self.timestamp)
+    }
+}
+
+impl Block {
+    /// Returns `true` if `value` is a block object for a pending block, which has a `null` hash
+    /// and number.
+    #[cfg(feature = "http")]
+    fn is_pending(value: &Value) -> bool {
+        value.get("hash") == Some(&Value::Null) || value.get("number") == Some(&Value::Null)
+    }
+
+    /// Parses the fields of a block object returned by `eth_getBlockByNumber`.
+    #[cfg(feature = "http")]
+    fn from_value(value: &Value) -> Result<Self, String> {
+        Ok(Block {
+            hash: decode::hash(decode::required(value, "hash")?)?,
+            timestamp: decode::quantity(decode::required(value, "timestamp")?)?,
+            number: decode::quantity(decode::required(value, "number")?)?,
+        })
+    }
 }
 
 /// A reduced set of fields from an Eth1 contract log.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Log {
+    /// The contract which emitted the log.
+    pub(crate) address: H160,
+    /// The indexed event signature and arguments. For a `DepositEvent` this is only
+    /// `DEPOSIT_EVENT_TOPIC`, since none of its arguments are indexed.
+    pub(crate) topics: Vec<Hash256>,
     pub(crate) block_number: u64,
+    pub(crate) transaction_hash: Hash256,
+    /// The position of the log within its block, `None` if it is pending.
+    pub(crate) log_index: Option<u64>,
     pub(crate) data: Vec<u8>,
+    /// `true` if the log was from a block which has since been re-orged out.
+    pub(crate) removed: bool,
 }
 
-/// Returns logs for the `DEPOSIT_EVENT_TOPIC`, for the given `address` in the given
-/// `block_height_range`.
-///
-/// It's not clear from the Ethereum JSON-RPC docs if this range is inclusive or not.
-///
-/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
-pub async fn get_deposit_logs_in_range(
-    endpoint: &str,
-    address: &str,
-    block_height_range: Range<u64>,
-    timeout: Duration,
-) -> Result<usize, String> {
-    let params = json! ([{
-        "address": address,
-        "topics": [DEPOSIT_EVENT_TOPIC],
-        "fromBlock": format!("0x{:x}", block_height_range.start),
-        "toBlock": format!("0x{:x}", block_height_range.end),
-    }]);
-
-    let response_body = send_rpc_request(endpoint, "eth_getLogs", params, timeout).await?;
-    response_result(&response_body)?
-        .ok_or_else(|| "No result field was returned for deposit logs".to_string())?
-        .as_array()
-        // .cloned()
-        .ok_or_else(|| "'result' value was not an array".to_string())
-        .map(|a| a.len())
-}
-
-/// Sends an RPC request to `endpoint`, using a POST with the given `body`.
-///
-/// Tries to receive the response and parse the body as a `String`.
-pub async fn send_rpc_request(
-    endpoint: &str,
-    method: &str,
-    params: Value,
-    timeout: Duration,
-) -> Result<String, String> {
-    let body = json! ({
-        "jsonrpc": "2.0",
-        "method": method,
-        "params": params,
-        "id": 1
-    })
-    .to_string();
-
-    // Note: it is not ideal to create a new client for each request.
-    //
-    // A better solution would be to create some struct that contains a built client and pass it
-    // around (similar to the `web3` crate's `Transport` structs).
-    let response = ClientBuilder::new()
-        .timeout(timeout)
-        .build()
-        .expect("The builder should always build a client")
-        .post(endpoint)
-        .header(CONTENT_TYPE, "application/json")
-        .body(body)
-        .send()
-        .map_err(|e| format!("Request failed: {:?}", e))
-        .await?;
-    if response.status() != StatusCode::OK {
-        return Err(format!(
-            "Response HTTP status was not 200 OK:  {}.",
-            response.status()
-        ));
-    };
-    let encoding = response
-        .headers()
-        .get(CONTENT_TYPE)
-        .ok_or_else(|| "No content-type header in response".to_string())?
-        .to_str()
-        .map(|s| s.to_string())
-        .map_err(|e| format!("Failed to parse content-type header: {}", e))?;
-
-    response
-        .bytes()
-        .map_err(|e| format!("Failed to receive body: {:?}", e))
-        .await
-        .and_then(move |bytes| match encoding.as_str() {
-            "application/json" => Ok(bytes),
-            "application/json; charset=utf-8" => Ok(bytes),
-            other => Err(format!("Unsupported encoding: {}", other)),
-        })
-        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
-        .map_err(|e| format!("Failed to receive body: {:?}", e))
+impl fmt::Display for Log {
+    /// E.g., `log in block #11184524 (576 bytes of data)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "log in block #{} ({} bytes of data)",
+            self.block_number,
+            self.data.len()
+        )?;
+        if self.removed {
+            write!(f, ", removed")?;
+        }
+        Ok(())
+    }
 }
 
-/// Accepts an entire HTTP body (as a string) and returns the `result` field, as a serde `Value`.
-fn response_result(response: &str) -> Result<Option<Value>, String> {
-    let json = serde_json::from_str::<Value>(&response)
-        .map_err(|e| format!("Failed to parse response: {:?}", e))?;
+/// The sync status of an eth1 node, as reported by `eth_syncing`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SyncStatus {
+    Synced,
+    Syncing {
+        starting_block: u64,
+        current_block: u64,
+        highest_block: u64,
+    },
+}
 
-    if let Some(error) = json.get("error") {
-        Err(format!("Eth1 node returned error: {}", error))
-    } else {
-        Ok(json
-            .get("result")
-            .cloned()
-            .map(Some)
-            .unwrap_or_else(|| None))
+/// Parameters which are expected to differ between eth1 networks.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NetworkParams {
+    /// Number of blocks behind the head which are considered safe from re-orgs.
+    pub follow_distance: u64,
+    /// Number of blocks to request in a single `eth_getLogs` call.
+    pub log_chunk_size: u64,
+    /// The expected time between eth1 blocks, i.e., `SECONDS_PER_ETH1_BLOCK`.
+    pub seconds_per_eth1_block: u64,
+    /// The length of a beacon chain eth1 voting period, i.e.,
+    /// `EPOCHS_PER_ETH1_VOTING_PERIOD * SLOTS_PER_EPOCH * SECONDS_PER_SLOT`.
+    pub eth1_voting_period_seconds: u64,
+}
+
+impl NetworkParams {
+    /// Returns the default parameters for the given network.
+    ///
+    /// Mainnet has a much higher density of deposit logs than the testnets, so it uses a smaller
+    /// chunk size to keep responses within common provider limits.
+    pub fn for_network(id: &Eth1Id) -> Self {
+        match id {
+            Eth1Id::Mainnet => Self::default(),
+            Eth1Id::Goerli | Eth1Id::Sepolia | Eth1Id::Holesky => Self {
+                log_chunk_size: 5000,
+                ..Self::default()
+            },
+            Eth1Id::Custom(_) => Self::default(),
+        }
     }
 }
 
-/// Parses a `0x`-prefixed, **big-endian** hex string as a u64.
-///
-/// Note: the JSON-RPC encodes integers as big-endian. The deposit contract uses little-endian.
-/// Therefore, this function is only useful for numbers encoded by the JSON RPC.
-///
-/// E.g., `0x01 == 1`
-fn hex_to_u64_be(hex: &str) -> Result<u64, String> {
-    u64::from_str_radix(strip_prefix(hex)?, 16)
-        .map_err(|e| format!("Failed to parse hex as u64: {:?}", e))
+impl Default for NetworkParams {
+    /// Conservative parameters, suitable for mainnet and for networks which are not known.
+    fn default() -> Self {
+        Self {
+            follow_distance: 2048,
+            log_chunk_size: 1000,
+            seconds_per_eth1_block: 14,
+            eth1_voting_period_seconds: 64 * 32 * 12,
+        }
+    }
+}
+
+/// Decodes the return value of `get_deposit_root()`, returning `None` if it is empty (i.e., the
+/// contract does not exist).
+#[cfg(feature = "http")]
+fn decode_deposit_root(bytes: &[u8]) -> Result<Option<Hash256>, String> {
+    if bytes.is_empty() {
+        Ok(None)
+    } else if bytes.len() == DEPOSIT_ROOT_BYTES {
+        Ok(Some(Hash256::from_slice(bytes)))
+    } else {
+        Err(format!(
+            "Deposit root response was not {} bytes: {:?}",
+            DEPOSIT_ROOT_BYTES, bytes
+        ))
+    }
 }
 
-/// Removes the `0x` prefix from some bytes. Returns an error if the prefix is not present.
-fn strip_prefix(hex: &str) -> Result<&str, String> {
-    if hex.starts_with("0x") {
-        Ok(&hex[2..])
+/// Decodes the return value of `get_deposit_count()`, returning `None` if it is empty (i.e., the
+/// contract does not exist).
+#[cfg(feature = "http")]
+fn decode_deposit_count(bytes: &[u8]) -> Result<Option<u64>, String> {
+    if bytes.is_empty() {
+        Ok(None)
+    } else if bytes.len() == DEPOSIT_COUNT_RESPONSE_BYTES {
+        let mut array = [0; 8];
+        array.copy_from_slice(&bytes[32 + 32..32 + 32 + 8]);
+        Ok(Some(u64::from_le_bytes(array)))
     } else {
-        Err("Hex string did not start with `0x`".to_string())
+        Err(format!(
+            "Deposit count response was not {} bytes: {:?}",
+            DEPOSIT_COUNT_RESPONSE_BYTES, bytes
+        ))
     }
 }
@@ -0,0 +1,166 @@
+//! Finding the widest `eth_getLogs` range that an endpoint allows, since hosted providers each
+//! have their own limit and few document it precisely.
+
+use crate::{Eth1Client, Eth1Error};
+use std::future::Future;
+use std::ops::Range;
+
+/// The widths of the ranges requested by `Eth1Client::detect_max_block_range`, narrowest first.
+const PROBE_RANGES: &[u64] = &[100, 500, 1_000, 2_000, 5_000, 10_000, 50_000, 100_000];
+
+impl Eth1Client {
+    /// Returns the widest `eth_getLogs` range allowed by the endpoint, and caps
+    /// `Self::log_chunk_size` to it for this client and its clones.
+    ///
+    /// Requests the logs of `address` in ranges of increasing width ending at the head, stopping
+    /// at the first which is rejected as too wide (see `is_range_limit`). The result is the
+    /// widest range which succeeded, so is at most the widest range probed; an endpoint without a
+    /// limit is treated as having that limit.
+    ///
+    /// Returns an error if even the narrowest range fails, or if any range fails for some other
+    /// reason (e.g., a timeout or rate limit), since that says nothing about the limit.
+    pub async fn detect_max_block_range(&self, address: &str) -> Result<u64, Eth1Error> {
+        let head = self.get_block_number().await?;
+        let max = probe_max_block_range(head, |range| async move {
+            self.get_deposit_logs_in_range(address, range)
+                .await
+                .map(|_| ())
+        })
+        .await?;
+        self.set_detected_max_block_range(max);
+        Ok(max)
+    }
+}
+
+/// Returns the widest of the `PROBE_RANGES` ending at `head` for which `get_logs` succeeds, as
+/// per `Eth1Client::detect_max_block_range`.
+async fn probe_max_block_range<F, Fut>(head: u64, mut get_logs: F) -> Result<u64, Eth1Error>
+where
+    F: FnMut(Range<u64>) -> Fut,
+    Fut: Future<Output = Result<(), Eth1Error>>,
+{
+    let mut max = None;
+    for width in PROBE_RANGES {
+        let start = (head + 1).saturating_sub(*width);
+        match get_logs(start..head + 1).await {
+            Ok(()) => {
+                max = Some(*width);
+                // The chain is no longer than this range, so a wider one tells us nothing.
+                if start == 0 {
+                    break;
+                }
+            }
+            Err(e) if max.is_some() && is_range_limit(&e) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(max.unwrap_or(PROBE_RANGES[0]))
+}
+
+/// Returns `true` if `error` is a rejection of an `eth_getLogs` range as too wide, rather than a
+/// failure which a narrower range would meet too.
+///
+/// As well as a non-transient RPC error or an oversized response, this includes a range which
+/// exceeds the configured `max_block_range`, which is a limit all the same.
+fn is_range_limit(error: &Eth1Error) -> bool {
+    match error {
+        Eth1Error::Rpc { .. } => !error.is_transient(),
+        Eth1Error::ResponseTooLarge { .. } | Eth1Error::InvalidRequest(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::ready;
+
+    fn too_many_results() -> Eth1Error {
+        Eth1Error::Rpc {
+            code: -32005,
+            message: "query returned more than 10000 results".to_string(),
+        }
+    }
+
+    /// Probes an endpoint that allows ranges of up to `limit` blocks, failing wider ones with
+    /// `error`.
+    async fn probe(head: u64, limit: u64, error: fn() -> Eth1Error) -> Result<u64, Eth1Error> {
+        probe_max_block_range(head, |range| {
+            ready(if range.end - range.start <= limit {
+                Ok(())
+            } else {
+                Err(error())
+            })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn range_limit_errors_end_the_probe() {
+        assert_eq!(
+            probe(1_000_000, 2_000, too_many_results).await.unwrap(),
+            2_000
+        );
+        assert_eq!(
+            probe(1_000_000, 5_000, || Eth1Error::ResponseTooLarge {
+                limit: 1
+            })
+            .await
+            .unwrap(),
+            5_000
+        );
+        assert_eq!(
+            probe(1_000_000, 10_000, || Eth1Error::InvalidRequest(
+                "too wide".to_string()
+            ))
+            .await
+            .unwrap(),
+            10_000
+        );
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_returned() {
+        for error in [
+            || Eth1Error::Timeout,
+            || Eth1Error::HttpStatus(429),
+            || Eth1Error::Connection("refused".to_string()),
+            || Eth1Error::Rpc {
+                code: crate::INTERNAL_ERROR_CODE,
+                message: "internal error".to_string(),
+            },
+        ] {
+            assert_eq!(probe(1_000_000, 2_000, error).await, Err(error()));
+        }
+    }
+
+    #[tokio::test]
+    async fn failure_of_the_narrowest_range_is_returned() {
+        assert_eq!(
+            probe(1_000_000, 10, too_many_results).await,
+            Err(too_many_results())
+        );
+    }
+
+    #[tokio::test]
+    async fn endpoints_without_a_limit_have_the_widest_probe() {
+        assert_eq!(
+            probe(1_000_000, u64::MAX, too_many_results).await.unwrap(),
+            100_000
+        );
+    }
+
+    #[tokio::test]
+    async fn probing_stops_at_genesis() {
+        let mut widths = vec![];
+        let max = probe_max_block_range(700, |range| {
+            widths.push(range.end - range.start);
+            ready(Ok(()))
+        })
+        .await
+        .unwrap();
+        assert_eq!(max, 1_000);
+        assert_eq!(widths, vec![100, 500, 701]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Decoding of `eth_getLogs` responses.
+//!
+//! Responses are deserialized directly into `Log`s as each array element is parsed, rather than
+//! first building a `serde_json::Value` of the entire response. A response covering a busy range
+//! can be several megabytes, so this avoids holding multiple copies of it in memory at once.
+//!
+//! `read_logs_response` goes further and parses the body as it arrives, so that the body is
+//! never held in memory at all. `decode_logs_response` decodes a body which has already been
+//! buffered, e.g., for the response cache.
+
+use crate::decode::{hex_to_address, hex_to_bytes, hex_to_hash, hex_to_u64_be};
+use crate::Log;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+
+/// The fields of a JSON-RPC response to `eth_getLogs`.
+#[derive(Deserialize)]
+struct LogsResponse {
+    result: Option<Logs>,
+    error: Option<Value>,
+}
+
+/// The `result` of an `eth_getLogs` response, decoded element-by-element.
+struct Logs(Vec<Log>);
+
+/// The fields of a log object that are used by this crate. Values are borrowed from the body
+/// where possible.
+#[derive(Deserialize)]
+struct RawLog<'a> {
+    #[serde(borrow)]
+    address: Cow<'a, str>,
+    #[serde(borrow)]
+    topics: Vec<Cow<'a, str>>,
+    #[serde(rename = "blockNumber", borrow)]
+    block_number: Cow<'a, str>,
+    #[serde(rename = "transactionHash", borrow)]
+    transaction_hash: Cow<'a, str>,
+    /// `null` for a pending log.
+    #[serde(rename = "logIndex", borrow, default)]
+    log_index: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    data: Cow<'a, str>,
+    /// Absent from the responses of some nodes, in which case the log is current.
+    #[serde(default)]
+    removed: bool,
+}
+
+impl<'a> RawLog<'a> {
+    fn decode(&self) -> Result<Log, String> {
+        Ok(Log {
+            address: hex_to_address(&self.address)?,
+            topics: self
+                .topics
+                .iter()
+                .map(|topic| hex_to_hash(topic))
+                .collect::<Result<_, _>>()?,
+            block_number: hex_to_u64_be(&self.block_number)?,
+            transaction_hash: hex_to_hash(&self.transaction_hash)?,
+            log_index: self
+                .log_index
+                .as_ref()
+                .map(|index| hex_to_u64_be(index))
+                .transpose()?,
+            data: hex_to_bytes(&self.data)?,
+            removed: self.removed,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Logs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LogsVisitor;
+
+        impl<'de> Visitor<'de> for LogsVisitor {
+            type Value = Logs;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an array of logs")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Logs, A::Error> {
+                let mut logs = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(raw) = seq.next_element::<RawLog<'de>>()? {
+                    logs.push(raw.decode().map_err(de::Error::custom)?);
+                }
+                Ok(Logs(logs))
+            }
+        }
+
+        deserializer.deserialize_seq(LogsVisitor)
+    }
+}
+
+/// Decodes an entire HTTP body of an `eth_getLogs` response into `Log`s.
+///
+/// The logs are sorted by block number and log index, since some nodes return them in
+/// descending order, and deposits must be processed in the order they were made.
+pub fn decode_logs_response(body: &[u8]) -> Result<Vec<Log>, String> {
+    let response = serde_json::from_slice::<LogsResponse>(body)
+        .map_err(|e| format!("Failed to parse response: {:?}", e))?;
+
+    if let Some(error) = response.error {
+        Err(format!("Eth1 node returned error: {}", error))
+    } else {
+        let logs = response
+            .result
+            .map(|logs| logs.0)
+            .ok_or_else(|| "No result field was returned for deposit logs".to_string())?;
+        Ok(sorted(logs))
+    }
+}
+
+/// Decodes an `eth_getLogs` response into `Log`s as it is read from `reader`, e.g., a
+/// `ChunkReader` over a body which is still arriving.
+///
+/// Returns the JSON-RPC `error` object in place of the logs if the response has one, so that the
+/// caller can classify it. The logs are sorted as per `decode_logs_response`.
+#[cfg(feature = "http")]
+pub(crate) fn read_logs_response<R: std::io::Read>(
+    reader: R,
+) -> Result<Result<Vec<Log>, Value>, String> {
+    let response = serde_json::from_reader::<_, LogsResponse>(reader)
+        .map_err(|e| format!("Failed to parse response: {:?}", e))?;
+
+    match response {
+        LogsResponse {
+            error: Some(error), ..
+        } => Ok(Err(error)),
+        LogsResponse {
+            result: Some(logs), ..
+        } => Ok(Ok(sorted(logs.0))),
+        LogsResponse { result: None, .. } => {
+            Err("No result field was returned for deposit logs".to_string())
+        }
+    }
+}
+
+/// As per `decode_logs_response`, but also accepts a response whose `result` is the array of
+/// logs serialized as a JSON string, as returned by some nonconforming gateways.
+///
+/// The strict decoding is tried first, so a conforming response is decoded just as quickly.
+pub fn decode_logs_response_lenient(body: &[u8]) -> Result<Vec<Log>, String> {
+    let strict_error = match decode_logs_response(body) {
+        Ok(logs) => return Ok(logs),
+        Err(e) => e,
+    };
+
+    #[derive(Deserialize)]
+    struct StringResult {
+        result: String,
+    }
+    match serde_json::from_slice::<StringResult>(body) {
+        Ok(response) => serde_json::from_str::<Logs>(&response.result)
+            .map(|logs| sorted(logs.0))
+            .map_err(|e| format!("Failed to parse stringified result: {:?}", e)),
+        Err(_) => Err(strict_error),
+    }
+}
+
+/// Sorts `logs` by block number and log index.
+fn sorted(mut logs: Vec<Log>) -> Vec<Log> {
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{deposit, deposit_log_json, logs_response};
+    use crate::{decode_deposit_log, DepositLog};
+    use serde_json::json;
+
+    #[test]
+    fn logs_in_descending_order_are_sorted() {
+        let deposits = vec![deposit(0, 5), deposit(1, 5), deposit(2, 6), deposit(3, 9)];
+        // Newest first, as returned by some nodes, with log indices within each block.
+        let body = logs_response(vec![
+            deposit_log_json(&deposits[3], 0),
+            deposit_log_json(&deposits[2], 4),
+            deposit_log_json(&deposits[1], 7),
+            deposit_log_json(&deposits[0], 2),
+        ]);
+
+        for logs in [
+            decode_logs_response(&body).unwrap(),
+            decode_logs_response_lenient(&body).unwrap(),
+        ] {
+            let positions: Vec<_> = logs
+                .iter()
+                .map(|log| (log.block_number, log.log_index))
+                .collect();
+            assert_eq!(
+                positions,
+                vec![(5, Some(2)), (5, Some(7)), (6, Some(4)), (9, Some(0))]
+            );
+            let decoded: Vec<DepositLog> = logs
+                .iter()
+                .map(|log| decode_deposit_log(log).unwrap())
+                .collect();
+            assert_eq!(decoded, deposits);
+        }
+    }
+
+    #[test]
+    fn lenient_decoding_accepts_a_stringified_result() {
+        let logs = vec![
+            deposit_log_json(&deposit(0, 5), 0),
+            deposit_log_json(&deposit(1, 6), 1),
+        ];
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": Value::Array(logs.clone()).to_string(),
+        })
+        .to_string();
+
+        assert!(decode_logs_response(body.as_bytes()).is_err());
+        assert_eq!(
+            decode_logs_response_lenient(body.as_bytes()).unwrap(),
+            decode_logs_response(&logs_response(logs)).unwrap()
+        );
+    }
+
+    #[test]
+    fn lenient_decoding_rejects_an_unparseable_body() {
+        for body in [
+            &b"<html>502 Bad Gateway</html>"[..],
+            br#"{"jsonrpc":"2.0","id":1,"result":"not logs"}"#,
+            br#"{"jsonrpc":"2.0","id":1,"result":42}"#,
+        ] {
+            assert!(decode_logs_response_lenient(body).is_err());
+        }
+    }
+}
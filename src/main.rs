@@ -1,12 +1,24 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
 use deposits_script::*;
-use futures::prelude::*;
+use ethereum_types::{H160, H256};
+use futures::stream::FuturesUnordered;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::ops::Range;
-use std::time::Duration;
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
 
 const START_BLOCK: u64 = 3743587;
-const DEPOSIT_CONTRACT: &'static str = "0x8c5fecdC472E27Bc447696F431E425D02dd46a8c"; // Pyrmont
-const ENDPOINT: &'static str = "http://192.168.1.10:8545";
+const DEPOSIT_CONTRACT: &str = "0x8c5fecdC472E27Bc447696F431E425D02dd46a8c"; // Pyrmont
+const ENDPOINT: &str = "http://192.168.1.10:8545";
 const TIMEOUT: Duration = Duration::from_millis(15000);
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[allow(dead_code)]
 async fn get_logs_and_drop(range: Range<u64>) {
@@ -16,55 +28,1126 @@ async fn get_logs_and_drop(range: Range<u64>) {
     );
 
     match get_deposit_logs_in_range(ENDPOINT, DEPOSIT_CONTRACT, range.clone(), TIMEOUT).await {
-        Ok(logs) => println!("Got {} logs in range {}..{}", logs, range.start, range.end),
+        Ok(logs) => println!(
+            "Got {} logs in range {}..{}",
+            logs.len(),
+            range.start,
+            range.end
+        ),
         Err(e) => println!("Got error: {:?}", e),
     }
 }
 
-#[allow(dead_code)]
-async fn stream_responses(
-    range_chunks: Vec<Range<u64>>,
-) -> Result<Vec<(Range<u64>, usize)>, String> {
-    let logs: Vec<(Range<u64>, usize)> =
-        stream::try_unfold(range_chunks.into_iter(), |mut chunks| async {
-            match chunks.next() {
-                Some(chunk) => {
-                    let chunk_1 = chunk.clone();
-                    match get_deposit_logs_in_range(&ENDPOINT, &DEPOSIT_CONTRACT, chunk, TIMEOUT)
-                        .await
-                    {
-                        Ok(logs) => Ok(Some(((chunk_1, logs), chunks))),
-                        Err(e) => Err(e),
-                    }
+fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    let block = Arg::with_name("block")
+        .long("block")
+        .value_name("BLOCK")
+        .help("Block number or hash to read the contract at, or `latest`, `safe` or `finalized`")
+        .takes_value(true)
+        .default_value("latest");
+
+    App::new("deposits_script")
+        .about("Reads the eth2 deposit contract via an eth1 JSON-RPC endpoint")
+        .arg(
+            Arg::with_name("endpoint")
+                .long("endpoint")
+                .value_name("URL")
+                .help("Eth1 HTTP JSON-RPC endpoint")
+                .takes_value(true)
+                .default_value(ENDPOINT)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("contract")
+                .long("contract")
+                .value_name("ADDRESS")
+                .help("Address of the deposit contract")
+                .takes_value(true)
+                .default_value(DEPOSIT_CONTRACT)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("topic")
+                .long("topic")
+                .value_name("TOPIC")
+                .help("First topic of the contract's deposit logs, for deposit-like contracts")
+                .takes_value(true)
+                .default_value(DEPOSIT_EVENT_TOPIC)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Timeout for each request")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("retries")
+                .long("retries")
+                .value_name("N")
+                .help("Times to retry each request which fails with a transient error")
+                .takes_value(true)
+                .default_value("3")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("retry-budget")
+                .long("retry-budget")
+                .value_name("N")
+                .help("Total retries allowed across the whole run, unlimited if not given")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("chunk-size")
+                .long("chunk-size")
+                .value_name("BLOCKS")
+                .help(
+                    "Blocks per eth_getLogs request. Hosted providers often reject more than \
+                     1000-2000, a local node is fine with 10000",
+                )
+                .takes_value(true)
+                .default_value("1000")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .long("concurrency")
+                .value_name("N")
+                .help(
+                    "eth_getLogs requests in flight at once. Use 1-4 for rate-limited hosted \
+                     providers, 8 or more for a local node",
+                )
+                .takes_value(true)
+                .default_value("4"),
+        )
+        .arg(
+            Arg::with_name("max-in-flight-bytes")
+                .long("max-in-flight-bytes")
+                .value_name("BYTES")
+                .help("Pause new eth_getLogs requests while their estimated responses exceed BYTES")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("detect-log-limit")
+                .long("detect-log-limit")
+                .help("Probe the widest eth_getLogs range the endpoint allows, and chunk below it"),
+        )
+        .arg(
+            Arg::with_name("state-file")
+                .long("state-file")
+                .value_name("PATH")
+                .help("JSON file to save sync progress to, and resume from if it exists")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-head-age")
+                .long("max-head-age")
+                .value_name("SECONDS")
+                .help("Warn if the head block is older than this, as the node may be stuck")
+                .takes_value(true)
+                .default_value("300"),
+        )
+        .arg(
+            Arg::with_name("verify-root")
+                .long("verify-root")
+                .help("Build the deposit tree and check its root against the contract at the end"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the outcome of the sync as a single JSON object"),
+        )
+        .arg(
+            Arg::with_name("since-timestamp")
+                .long("since-timestamp")
+                .value_name("TIME")
+                .help("Sync from the first block at or after TIME, as RFC3339 or unix seconds")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .help("After the sync, keep polling for new deposits and print them until Ctrl-C"),
+        )
+        .arg(
+            Arg::with_name("poll-interval")
+                .long("poll-interval")
+                .value_name("DURATION")
+                .help(
+                    "Time between polls with --watch, in seconds (e.g., 12s) or minutes (e.g., 5m)",
+                )
+                .takes_value(true)
+                .default_value("12s"),
+        )
+        .arg(
+            Arg::with_name("alert-webhook")
+                .long("alert-webhook")
+                .value_name("URL")
+                .help("With --watch, POST each new deposit as JSON to URL")
+                .takes_value(true)
+                .requires("watch"),
+        )
+        .arg(
+            Arg::with_name("alert-exec")
+                .long("alert-exec")
+                .value_name("COMMAND")
+                .help("With --watch, run COMMAND with `sh -c` for each new deposit, with its JSON on stdin")
+                .takes_value(true)
+                .requires("watch"),
+        )
+        .arg(
+            Arg::with_name("alert-pubkey")
+                .long("alert-pubkey")
+                .value_name("PUBKEY")
+                .help("Only alert on deposits for this validator public key, may be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("watch"),
+        )
+        .arg(
+            Arg::with_name("alert-withdrawal-address")
+                .long("alert-withdrawal-address")
+                .value_name("ADDRESS")
+                .help("Only alert on deposits which withdraw to this address, may be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("watch"),
+        )
+        .subcommand(
+            SubCommand::with_name("root")
+                .about("Prints the deposit root at a block")
+                .arg(block.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("count")
+                .about("Prints the deposit count at a block")
+                .arg(block),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Finds the deposits for a validator public key")
+                .arg(
+                    Arg::with_name("pubkey")
+                        .long("pubkey")
+                        .value_name("PUBKEY")
+                        .help("0x-prefixed validator public key")
+                        .takes_value(true)
+                        .required_unless("file"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .value_name("PATH")
+                        .help("deposit_data-*.json file whose every entry should be on chain")
+                        .takes_value(true)
+                        .conflicts_with("pubkey"),
+                )
+                .arg(
+                    Arg::with_name("from-block")
+                        .long("from-block")
+                        .value_name("BLOCK")
+                        .help("First block to search, defaults to the contract deployment block")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Writes every deposit as CSV or NDJSON, one row at a time")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format, NDJSON has one JSON object per line")
+                        .possible_values(&["csv", "ndjson"])
+                        .default_value("csv")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("File to write to, defaults to stdout")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from-block")
+                        .long("from-block")
+                        .value_name("BLOCK")
+                        .help("First block to export, defaults to the contract deployment block")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Makes the deposits of a deposit data file, e.g., on a local test node")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .value_name("PATH")
+                        .help("deposit_data-*.json file whose entries should be deposited")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("ADDRESS")
+                        .help("Account to send from, which the node must sign for")
+                        .takes_value(true)
+                        .required_unless("dry-run"),
+                )
+                .arg(Arg::with_name("dry-run").long("dry-run").help(
+                    "Print the value and calldata of each transaction instead of sending it",
+                )),
+        )
+}
+
+/// Prints `error` and exits with a non-zero status.
+fn exit_with<E: std::fmt::Display>(error: E) -> ! {
+    eprintln!("Error: {}", error);
+    process::exit(1)
+}
+
+/// Returns `--from-block`, or the block in which `contract` was deployed.
+///
+/// The deployment block of the default contract is known, any other is found with
+/// `Eth1Client::find_deployment_block`, which needs an archive node.
+async fn parse_from_block(client: &Eth1Client, contract: &str, matches: &ArgMatches<'_>) -> u64 {
+    match matches.value_of("from-block") {
+        Some(block) => block
+            .parse()
+            .unwrap_or_else(|e| exit_with(format!("Invalid block {}: {}", block, e))),
+        None if contract.eq_ignore_ascii_case(DEPOSIT_CONTRACT) => START_BLOCK,
+        None => client
+            .find_deployment_block(contract)
+            .await
+            .unwrap_or_else(|e| exit_with(format!("Failed to find the deployment block: {}", e))),
+    }
+}
+
+fn parse_block(matches: &ArgMatches) -> BlockQuery {
+    matches
+        .value_of("block")
+        .unwrap_or("latest")
+        .parse()
+        .unwrap_or_else(|e| exit_with(e))
+}
+
+async fn verify(client: &Eth1Client, contract: &str, matches: &ArgMatches<'_>) {
+    if let Some(path) = matches.value_of("file") {
+        let from_block = parse_from_block(client, contract, matches).await;
+        return verify_file(client, contract, Path::new(path), from_block).await;
+    }
+    let pubkey = matches.value_of("pubkey").unwrap_or_default();
+    let pubkey: PublicKey = pubkey
+        .parse()
+        .unwrap_or_else(|e| exit_with(format!("Invalid pubkey {}: {}", pubkey, e)));
+    let from_block = parse_from_block(client, contract, matches).await;
+    let end_block = client
+        .get_block_number()
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+
+    let deposits = client
+        .find_deposits_by_pubkey(contract, &pubkey, from_block..end_block + 1)
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+
+    if deposits.is_empty() {
+        println!(
+            "Not found: no deposits for {} between blocks {} and {}",
+            pubkey, from_block, end_block
+        );
+    }
+    for deposit in deposits {
+        println!("Deposit {}", deposit.index);
+        println!(
+            "  amount: {} ETH ({} gwei)",
+            deposit.amount_eth_string(),
+            deposit.amount_gwei()
+        );
+        println!(
+            "  withdrawal credentials: {}",
+            deposit.withdrawal_credentials
+        );
+        println!("  block: {}", deposit.block_number);
+        println!("  transaction: {:?}", deposit.transaction_hash);
+    }
+}
+
+/// Checks that each entry of the deposit data file at `path` has a matching deposit from
+/// `from_block` onwards, exiting with a non-zero status if any does not.
+async fn verify_file(client: &Eth1Client, contract: &str, path: &Path, from_block: u64) {
+    let json = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| exit_with(format!("Failed to read {}: {}", path.display(), e)));
+    let entries = parse_deposit_data(&json).unwrap_or_else(|e| exit_with(e));
+    let report = client
+        .verify_deposit_data(contract, &entries, Some(from_block))
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+
+    for entry in &report.entries {
+        match &entry.result {
+            DepositVerification::Found {
+                index,
+                block_number,
+            } => println!(
+                "{}: OK, deposit {} in block {}",
+                entry.pubkey, index, block_number
+            ),
+            DepositVerification::Mismatch { index, fields } => {
+                println!("{}: MISMATCH, deposit {} differs", entry.pubkey, index);
+                for mismatch in fields {
+                    println!("  {}:", mismatch.field);
+                    println!("    expected: {}", mismatch.expected);
+                    println!("    on chain: {}", mismatch.on_chain);
                 }
-                None => Ok(None),
             }
+            DepositVerification::NotFound => println!("{}: NOT FOUND", entry.pubkey),
+        }
+    }
+    if !report.is_ok() {
+        process::exit(1);
+    }
+}
+
+/// Sends a `deposit` transaction for each entry of the deposit data file given by `--file`, or
+/// prints the transactions with `--dry-run`.
+async fn replay(client: &Eth1Client, contract: &str, matches: &ArgMatches<'_>) {
+    let path = matches.value_of("file").unwrap_or_default();
+    let json = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| exit_with(format!("Failed to read {}: {}", path, e)));
+    let deposits: Vec<DepositData> = parse_deposit_data(&json)
+        .and_then(|entries| {
+            entries
+                .iter()
+                .map(|entry| entry.to_deposit_data())
+                .collect()
         })
-        .try_collect()
-        .await?;
-    Ok(logs)
+        .unwrap_or_else(|e| exit_with(e));
+
+    if matches.is_present("dry-run") {
+        for deposit in &deposits {
+            println!("Deposit {}", PublicKey(deposit.pubkey));
+            println!("  value: {} wei", deposit_value_wei(deposit.amount));
+            println!("  calldata: {}", deposit_calldata(deposit));
+        }
+        return;
+    }
+    let from = matches.value_of("from").unwrap_or_default();
+    let hashes = client
+        .submit_deposits(from, contract, &deposits)
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+    for (deposit, hash) in deposits.iter().zip(hashes) {
+        println!(
+            "Deposited {} in transaction {:?}",
+            PublicKey(deposit.pubkey),
+            hash
+        );
+    }
+}
+
+/// Streams the deposits from `--from-block` to the head into a CSV or NDJSON file, or stdout,
+/// writing each chunk of deposits as it arrives.
+async fn export(client: &Eth1Client, contract: &str, matches: &ArgMatches<'_>) {
+    let from_block = parse_from_block(client, contract, matches).await;
+    let writer: Box<dyn Write> = match matches.value_of("output") {
+        Some(path) => Box::new(
+            File::create(path)
+                .unwrap_or_else(|e| exit_with(format!("Failed to create {}: {}", path, e))),
+        ),
+        None => Box::new(io::stdout()),
+    };
+    let writer = BufWriter::new(writer);
+    let end_block = client
+        .get_block_number()
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+    let range = from_block..end_block + 1;
+
+    let rows = match matches.value_of("format") {
+        Some("ndjson") => {
+            let mut ndjson = DepositNdjsonWriter::new(writer);
+            write_deposits(client, contract, range, |deposit| ndjson.write(deposit)).await;
+            let rows = ndjson.rows();
+            ndjson.finish().unwrap_or_else(|e| exit_with(e));
+            rows
+        }
+        _ => {
+            let mut csv = DepositCsvWriter::new(writer).unwrap_or_else(|e| exit_with(e));
+            write_deposits(client, contract, range, |deposit| csv.write(deposit)).await;
+            let rows = csv.rows();
+            csv.finish().unwrap_or_else(|e| exit_with(e));
+            rows
+        }
+    };
+    eprintln!(
+        "Exported {} deposits from blocks {} to {}",
+        rows, from_block, end_block
+    );
+}
+
+/// Passes each deposit in `range` to `write` as it arrives, exiting on the first error.
+async fn write_deposits<F>(client: &Eth1Client, contract: &str, range: Range<u64>, mut write: F)
+where
+    F: FnMut(&DepositLog) -> Result<(), String>,
+{
+    let mut deposits = Box::pin(client.deposit_stream(contract, range));
+    while let Some(deposit) = deposits.try_next().await.unwrap_or_else(|e| exit_with(e)) {
+        write(&deposit).unwrap_or_else(|e| exit_with(e));
+    }
+}
+
+/// Parses the value of the argument `name`, exiting if it is not a number of at least 1.
+fn parse_at_least_one(matches: &ArgMatches, name: &str) -> u64 {
+    let value = matches.value_of(name).unwrap_or("1");
+    match value.parse() {
+        Ok(n) if n >= 1 => n,
+        _ => exit_with(format!("Invalid {} {}: must be at least 1", name, value)),
+    }
+}
+
+/// Parses the value of the argument `name`, if given, exiting if it is not a number.
+fn parse_count(matches: &ArgMatches, name: &str) -> Option<usize> {
+    matches.value_of(name).map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|e| exit_with(format!("Invalid {} {}: {}", name, value, e)))
+    })
+}
+
+/// Parses either unix seconds or an RFC3339 date and time, e.g., `2023-01-01T00:00:00Z`.
+fn parse_timestamp(s: &str) -> Result<u64, String> {
+    if let Ok(seconds) = s.parse::<u64>() {
+        return Ok(seconds);
+    }
+    let time = chrono::DateTime::parse_from_rfc3339(s)
+        .map_err(|e| format!("Invalid timestamp {}: {}", s, e))?;
+    u64::try_from(time.timestamp()).map_err(|_| format!("Timestamp {} is before 1970", s))
+}
+
+/// Prints a progress message to stdout, or to stderr if stdout is reserved for `--json` output.
+fn progress(json: bool, message: &str) {
+    if json {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// The outcome of a sync, as printed by `--json`.
+#[derive(Serialize)]
+struct SyncSummary {
+    start_block: u64,
+    end_block: u64,
+    /// The number of deposits seen in the logs.
+    deposits: u64,
+    contract_deposit_count: u64,
+    contract_deposit_root: H256,
+    /// The root of the deposits seen in the logs, only computed with `--verify-root`.
+    tree_root: Option<H256>,
+    /// `true` if the count (and the root, if computed) matched the contract.
+    matched: bool,
+    retries: usize,
+    duration_secs: f64,
+}
+
+/// Fetches the deposits from `start_block` to the head in chunks of `Eth1Client::log_chunk_size`
+/// blocks, with up to `concurrency` chunks in flight at once.
+///
+/// Chunks are recorded in order, so the state file is always consistent with a sequential sync.
+///
+/// If `verify_root`, the deposit tree is built from every deposit and its root checked against
+/// the contract, so the sync must start before the first deposit. If `json`, the outcome is
+/// printed to stdout as a single JSON object, and progress goes to stderr.
+///
+/// Returns the number of deposits, which matches the contract.
+async fn sync(
+    client: &Eth1Client,
+    contract: &str,
+    start_block: u64,
+    concurrency: usize,
+    state_file: Option<&Path>,
+    verify_root: bool,
+    json: bool,
+) -> u64 {
+    let started = Instant::now();
+    let network = client.get_chain_id().await.unwrap_or_else(|e| exit_with(e));
+    let mut state = match state_file.map(SyncState::load).transpose() {
+        Ok(Some(Some(state))) => {
+            state
+                .validate(contract, &network)
+                .unwrap_or_else(|e| exit_with(e));
+            progress(
+                json,
+                &format!(
+                    "Resuming from block {} with {} deposits",
+                    state.next_block(start_block),
+                    state.deposit_count
+                ),
+            );
+            state
+        }
+        Ok(_) => {
+            let mut state = SyncState::new(contract, network);
+            // Deposits are recorded without gaps, so start from the count before the first block.
+            if let Some(block) = start_block.checked_sub(1) {
+                state.deposit_count = client
+                    .get_deposit_count(contract, BlockQuery::Number(block))
+                    .await
+                    .unwrap_or_else(|e| exit_with(e))
+                    .unwrap_or(0);
+            }
+            state
+        }
+        Err(e) => exit_with(e),
+    };
+    if verify_root && state.deposit_count > 0 {
+        exit_with("--verify-root needs every deposit, so must start before the first deposit");
+    }
+    let mut tree = DepositTree::new();
+
+    let end_block = client
+        .get_block_number()
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+    let range = state.next_block(start_block)..end_block + 1;
+    progress(
+        json,
+        &format!(
+            "Syncing blocks {}..{} with {} eth_getLogs requests of {} blocks",
+            range.start,
+            range.end,
+            estimate_request_count(range.clone(), client.log_chunk_size()),
+            client.log_chunk_size()
+        ),
+    );
+    let sync_start = range.start;
+    let range_chunks = chunk_range(range, client.log_chunk_size());
+
+    // Chunks complete out of order, so each is held until every chunk before it has completed,
+    // then recorded and saved. On Ctrl-C the chunks in flight are dropped, and every recorded
+    // chunk has been saved, so the sync can be resumed from there.
+    let mut queued = range_chunks
+        .iter()
+        .map(|chunk| client.get_deposits_in_range(contract, chunk.clone()));
+    let mut in_flight: FuturesUnordered<_> = queued.by_ref().take(concurrency).collect();
+    let mut completed = BTreeMap::new();
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let response = tokio::select! {
+            response = in_flight.next() => Some(response),
+            _ = &mut ctrl_c => None,
+        };
+        let (chunk, deposits) = match response {
+            Some(Some(Ok(response))) => response,
+            Some(Some(Err(e))) => {
+                if client.retry_budget_exhausted() {
+                    eprintln!(
+                        "Retry budget exhausted after {} retries, aborting the sync",
+                        client.retries_used()
+                    );
+                }
+                exit_with(e)
+            }
+            Some(None) => break,
+            None => {
+                drop(in_flight);
+                client.shutdown().await;
+                interrupted(&state, state_file)
+            }
+        };
+        in_flight.extend(queued.next());
+        completed.insert(chunk.start, (chunk, deposits));
+
+        let mut recorded = false;
+        while let Some((chunk, deposits)) = completed.remove(&state.next_block(sync_start)) {
+            for deposit in &deposits {
+                if let Err(e) = deposit.validate_amount() {
+                    eprintln!("Warning: {}", e);
+                } else if deposit.exceeds_max_effective_balance() {
+                    eprintln!(
+                        "Warning: Deposit {} has an unexpected amount of {} gwei, above the max \
+                         effective balance of {} gwei",
+                        deposit.index,
+                        deposit.amount,
+                        deposit.max_effective_balance()
+                    );
+                }
+            }
+            if verify_root {
+                for deposit in &deposits {
+                    // Skip any deposit which the node returned twice.
+                    if deposit.index >= tree.len() {
+                        tree.push(deposit).unwrap_or_else(|e| exit_with(e));
+                    }
+                }
+            }
+            if let Err(e) = state.record(&chunk, &deposits) {
+                save_state(&state, state_file);
+                exit_with(e)
+            }
+            recorded = true;
+        }
+        if recorded {
+            save_state(&state, state_file);
+        }
+    }
+    progress(
+        json,
+        &format!(
+            "Got {} deposits, with {} retries",
+            state.deposit_count,
+            client.retries_used()
+        ),
+    );
+
+    let (contract_root, contract_count) = client
+        .get_root_and_count(contract, BlockQuery::Number(end_block))
+        .await
+        .unwrap_or_else(|e| exit_with(e));
+    let tree_root = if verify_root { Some(tree.root()) } else { None };
+    let summary = SyncSummary {
+        start_block: sync_start,
+        end_block,
+        deposits: state.deposit_count,
+        contract_deposit_count: contract_count,
+        contract_deposit_root: contract_root,
+        tree_root,
+        matched: state.deposit_count == contract_count
+            && tree_root.is_none_or(|root| root == contract_root),
+        retries: client.retries_used(),
+        duration_secs: started.elapsed().as_secs_f64(),
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&summary).unwrap_or_else(|e| exit_with(e))
+        );
+    } else {
+        print_reconciliation(&summary);
+    }
+    if summary.deposits != summary.contract_deposit_count {
+        exit_with(CheckError::CountMismatch {
+            expected: summary.contract_deposit_count,
+            actual: summary.deposits,
+        });
+    }
+    if let Some(tree_root) = summary.tree_root {
+        if tree_root != summary.contract_deposit_root {
+            exit_with(CheckError::RootMismatch {
+                expected: summary.contract_deposit_root,
+                actual: tree_root,
+            });
+        }
+    }
+    summary.deposits
+}
+
+/// Where to send alerts on new deposits in `--watch` mode, and which deposits to alert on.
+struct Alerts {
+    webhook: Option<String>,
+    command: Option<String>,
+    /// Alert only on deposits for these public keys, or for any if empty.
+    pubkeys: Vec<PublicKey>,
+    /// Alert only on deposits which withdraw to these addresses, or to any if empty.
+    withdrawal_addresses: Vec<H160>,
+    http: reqwest::Client,
+}
+
+impl Alerts {
+    /// Parses the `--alert-*` arguments, exiting if any is invalid.
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let values = |name| matches.values_of(name).into_iter().flatten();
+        Self {
+            webhook: matches.value_of("alert-webhook").map(String::from),
+            command: matches.value_of("alert-exec").map(String::from),
+            pubkeys: values("alert-pubkey")
+                .map(|pubkey| {
+                    pubkey
+                        .parse()
+                        .unwrap_or_else(|e| exit_with(format!("Invalid pubkey {}: {}", pubkey, e)))
+                })
+                .collect(),
+            withdrawal_addresses: values("alert-withdrawal-address")
+                .map(|address| {
+                    address.parse().unwrap_or_else(|e| {
+                        exit_with(format!("Invalid address {}: {:?}", address, e))
+                    })
+                })
+                .collect(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Returns `true` if `deposit` passes the pubkey and withdrawal address filters.
+    fn matches(&self, deposit: &DepositLog) -> bool {
+        let pubkey_matches = self.pubkeys.is_empty() || self.pubkeys.contains(&deposit.pubkey);
+        let address_matches = self.withdrawal_addresses.is_empty()
+            || deposit
+                .withdrawal_credentials
+                .eth1_address()
+                .is_some_and(|address| self.withdrawal_addresses.contains(&address));
+        pubkey_matches && address_matches
+    }
+
+    /// Sends `deposit` to the webhook and command, if it matches the filters.
+    ///
+    /// Alerts are best-effort: failures are printed as warnings, and do not stop the watch.
+    async fn notify(&self, deposit: &DepositLog) {
+        if !self.matches(deposit) {
+            return;
+        }
+        let json = match serde_json::to_vec(deposit) {
+            Ok(json) => json,
+            Err(e) => return eprintln!("Warning: failed to serialize deposit for alert: {}", e),
+        };
+        if let Some(url) = &self.webhook {
+            if let Err(e) = self.post(url, json.clone()).await {
+                eprintln!(
+                    "Warning: webhook alert for deposit {} failed: {}",
+                    deposit.index, e
+                );
+            }
+        }
+        if let Some(command) = &self.command {
+            if let Err(e) = run_alert_command(command, &json).await {
+                eprintln!(
+                    "Warning: command alert for deposit {} failed: {}",
+                    deposit.index, e
+                );
+            }
+        }
+    }
+
+    /// POSTs the JSON `body` to `url`, returning an error unless the response is a success.
+    async fn post(&self, url: &str, body: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(ALERT_TIMEOUT)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Response status was {}", response.status()))
+        }
+    }
+}
+
+/// Runs `command` with `sh -c`, writing `json` to its stdin, and returns an error unless it
+/// exits successfully within `ALERT_TIMEOUT`.
+async fn run_alert_command(command: &str, json: &[u8]) -> Result<(), String> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", command, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command which ignores its stdin may exit before it is written, which is fine.
+        let _ = stdin.write_all(json).await;
+    }
+    match tokio::time::timeout(ALERT_TIMEOUT, child).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(format!("{} exited with {}", command, status)),
+        Ok(Err(e)) => Err(format!("Failed to wait for {}: {}", command, e)),
+        Err(_) => Err(format!("{} timed out", command)),
+    }
+}
+
+/// Polls for deposits to `contract` with an index of at least `deposit_count` every
+/// `poll_interval`, printing each new deposit as it appears, until Ctrl-C. With `json`, each
+/// deposit is printed to stdout as a JSON object on its own line.
+///
+/// Each poll fetches the last deposit seen again, so a re-org which removes or replaces it is
+/// noticed. The deposits from its index are then printed again as they are on the new chain.
+/// Errors are printed as warnings and the poll retried, rather than ending the watch.
+///
+/// Each new deposit is also passed to `alerts`.
+async fn watch(
+    client: &Eth1Client,
+    contract: &str,
+    mut deposit_count: u64,
+    poll_interval: Duration,
+    alerts: &Alerts,
+    json: bool,
+) {
+    progress(
+        json,
+        &format!(
+            "Watching for deposits from index {}, polling every {:?}",
+            deposit_count, poll_interval
+        ),
+    );
+    let mut last: Option<DepositLog> = None;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            _ = tokio::time::delay_for(poll_interval) => {}
+            _ = &mut ctrl_c => return,
+        }
+
+        let deposits = match client
+            .fetch_new_deposits(contract, deposit_count.saturating_sub(1))
+            .await
+        {
+            Ok(deposits) => deposits,
+            Err(e) => {
+                eprintln!("Warning: failed to poll for deposits: {}", e);
+                continue;
+            }
+        };
+
+        let reorged = last.take().filter(|last| {
+            deposits.first().is_none_or(|first| {
+                first.index != last.index || first.transaction_hash != last.transaction_hash
+            })
+        });
+        if let Some(removed) = &reorged {
+            progress(
+                json,
+                &format!(
+                    "Re-org: deposit {} in block {} is no longer on chain",
+                    removed.index, removed.block_number
+                ),
+            );
+            deposit_count = removed.index;
+            if deposits.is_empty() {
+                // Earlier deposits may have been removed too, so start again from the count.
+                match client.get_deposit_count(contract, BlockQuery::Latest).await {
+                    Ok(count) => deposit_count = deposit_count.min(count.unwrap_or(0)),
+                    Err(e) => eprintln!("Warning: failed to get deposit count: {}", e),
+                }
+            }
+        }
+
+        for deposit in deposits.iter().filter(|d| d.index >= deposit_count) {
+            if json {
+                match serde_json::to_string(deposit) {
+                    Ok(line) => println!("{}", line),
+                    Err(e) => exit_with(e),
+                }
+            } else {
+                println!(
+                    "New deposit {}: {} ETH to {} in block {}, transaction {:?}",
+                    deposit.index,
+                    deposit.amount_eth_string(),
+                    deposit.pubkey,
+                    deposit.block_number,
+                    deposit.transaction_hash
+                );
+            }
+            alerts.notify(deposit).await;
+        }
+        if let Some(deposit) = deposits.last() {
+            deposit_count = deposit_count.max(deposit.index + 1);
+            last = Some(deposit.clone());
+        }
+    }
+}
+
+/// Parses a duration of whole seconds, e.g., `12` or `12s`, or minutes, e.g., `5m`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.strip_suffix('m') {
+        Some(minutes) => (minutes, 60),
+        None => (s.strip_suffix('s').unwrap_or(s), 1),
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n >= 1 => Ok(Duration::from_secs(n * unit)),
+        _ => Err(format!("Invalid duration {}: expected e.g. 12s or 5m", s)),
+    }
+}
+
+/// Saves `state` to `state_file`, if any, exiting if it cannot be written.
+fn save_state(state: &SyncState, state_file: Option<&Path>) {
+    if let Some(path) = state_file {
+        state.save(path).unwrap_or_else(|e| exit_with(e));
+    }
+}
+
+/// Saves `state` to `state_file`, if any, and exits after the sync was interrupted.
+fn interrupted(state: &SyncState, state_file: Option<&Path>) -> ! {
+    match state_file {
+        Some(path) => {
+            state.save(path).unwrap_or_else(|e| exit_with(e));
+            match state.last_synced_block {
+                Some(block) => eprintln!(
+                    "Interrupted, saved progress up to block {} to {}",
+                    block,
+                    path.display()
+                ),
+                None => eprintln!("Interrupted before any blocks were synced"),
+            }
+        }
+        None => eprintln!("Interrupted, no --state-file to save progress to"),
+    }
+    process::exit(130)
+}
+
+/// Prints the comparison of the deposits seen in the logs with the contract.
+fn print_reconciliation(summary: &SyncSummary) {
+    let result = |matched: bool| if matched { "MATCH" } else { "MISMATCH" };
+
+    println!();
+    println!("Reconciliation at block {}", summary.end_block);
+    println!("  Deposits in logs:        {}", summary.deposits);
+    println!(
+        "  Contract deposit count:  {}",
+        summary.contract_deposit_count
+    );
+    println!(
+        "  Result:                  {}",
+        result(summary.deposits == summary.contract_deposit_count)
+    );
+    if let Some(tree_root) = summary.tree_root {
+        println!("  Deposit tree root:       {:?}", tree_root);
+        println!(
+            "  Contract deposit root:   {:?}",
+            summary.contract_deposit_root
+        );
+        println!(
+            "  Result:                  {}",
+            result(tree_root == summary.contract_deposit_root)
+        );
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    let end_block = get_block_number(ENDPOINT, TIMEOUT).await.unwrap();
-    let range_chunks = (START_BLOCK..end_block)
-        .collect::<Vec<u64>>()
-        .chunks(1000)
-        .map(|vec| {
-            let first = vec.first().cloned().unwrap_or_else(|| 0);
-            let last = vec.last().map(|n| n + 1).unwrap_or_else(|| 0);
-            first..last
-        })
-        .collect::<Vec<Range<u64>>>();
-    println!("Number of chunks {}", range_chunks.len());
-    let resp = stream_responses(range_chunks).await.unwrap();
-    println!("Got {} responses", resp.len());
-    // for range in range_chunks {
-    //     let chain_id = get_chain_id(ENDPOINT, TIMEOUT).await;
-    //     println!("Chain id: {:?}", chain_id);
-    //     let network_id = get_network_id(ENDPOINT, TIMEOUT).await;
-    //     println!("Network id: {:?}", network_id);
-    //     get_logs_and_drop(range).await;
-    // }
+    let matches = cli_app().get_matches();
+    let endpoint = matches.value_of("endpoint").unwrap_or(ENDPOINT);
+    let deposit_config = DepositConfig::with_topic(
+        matches.value_of("contract").unwrap_or(DEPOSIT_CONTRACT),
+        matches.value_of("topic").unwrap_or(DEPOSIT_EVENT_TOPIC),
+    )
+    .unwrap_or_else(|e| exit_with(e));
+    let contract = deposit_config.contract.as_str();
+    let timeout = match matches.value_of("timeout") {
+        Some(secs) => Duration::from_secs(
+            secs.parse()
+                .unwrap_or_else(|e| exit_with(format!("Invalid timeout {}: {}", secs, e))),
+        ),
+        None => TIMEOUT,
+    };
+    let client = Eth1Client::with_config(
+        endpoint,
+        ClientConfig {
+            timeout,
+            log_chunk_size: Some(parse_at_least_one(&matches, "chunk-size")),
+            retry: RetryConfig {
+                max_retries: parse_count(&matches, "retries").unwrap_or(0),
+                retry_budget: parse_count(&matches, "retry-budget"),
+                ..RetryConfig::default()
+            },
+            deposit_topic: Some(deposit_config.topic.clone()),
+            max_in_flight_bytes: parse_count(&matches, "max-in-flight-bytes"),
+            ..ClientConfig::default()
+        },
+    );
+
+    match matches.subcommand() {
+        ("root", Some(sub_matches)) => {
+            match client
+                .get_deposit_root(contract, parse_block(sub_matches))
+                .await
+            {
+                Ok(Some(root)) => println!("{:?}", root),
+                Ok(None) => exit_with("Deposit contract is not deployed at that block"),
+                Err(e) => exit_with(e),
+            }
+        }
+        ("count", Some(sub_matches)) => {
+            match client
+                .get_deposit_count(contract, parse_block(sub_matches))
+                .await
+            {
+                Ok(Some(count)) => println!("{}", count),
+                Ok(None) => exit_with("Deposit contract is not deployed at that block"),
+                Err(e) => exit_with(e),
+            }
+        }
+        ("verify", Some(sub_matches)) => verify(&client, contract, sub_matches).await,
+        ("export", Some(sub_matches)) => export(&client, contract, sub_matches).await,
+        ("replay", Some(sub_matches)) => replay(&client, contract, sub_matches).await,
+        _ => {
+            let max_head_age = parse_count(&matches, "max-head-age").unwrap_or(300);
+            // Other errors are left for the sync to report.
+            if let Err(e @ Eth1Error::StaleHead { .. }) = client
+                .staleness_check(Duration::from_secs(max_head_age as u64))
+                .await
+            {
+                eprintln!("Warning: {}", e);
+            }
+            if let Ok(Some(warning)) = client.network_id_mismatch().await {
+                eprintln!("Warning: {}", warning);
+            }
+
+            if matches.is_present("detect-log-limit") {
+                let max = client
+                    .detect_max_block_range(contract)
+                    .await
+                    .unwrap_or_else(|e| exit_with(e));
+                progress(
+                    matches.is_present("json"),
+                    &format!(
+                        "Endpoint allows eth_getLogs ranges of up to {} blocks, using chunks of {}",
+                        max,
+                        client.log_chunk_size()
+                    ),
+                );
+            }
+
+            let start_block = match matches.value_of("since-timestamp") {
+                Some(time) => {
+                    let timestamp = parse_timestamp(time).unwrap_or_else(|e| exit_with(e));
+                    client
+                        .find_block_by_timestamp(timestamp)
+                        .await
+                        .unwrap_or_else(|e| exit_with(e))
+                }
+                None => START_BLOCK,
+            };
+            let poll_interval = parse_duration(matches.value_of("poll-interval").unwrap_or("12s"))
+                .unwrap_or_else(|e| exit_with(e));
+            let alerts = Alerts::from_matches(&matches);
+            let deposit_count = sync(
+                &client,
+                contract,
+                start_block,
+                parse_at_least_one(&matches, "concurrency") as usize,
+                matches.value_of("state-file").map(Path::new),
+                matches.is_present("verify-root"),
+                matches.is_present("json"),
+            )
+            .await;
+
+            if matches.is_present("watch") {
+                watch(
+                    &client,
+                    contract,
+                    deposit_count,
+                    poll_interval,
+                    &alerts,
+                    matches.is_present("json"),
+                )
+                .await
+            }
+        }
+    }
 }
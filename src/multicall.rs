@@ -0,0 +1,236 @@
+//! Reading the deposit root and count in a single `eth_call`, via a Multicall3 aggregator
+//! contract.
+//!
+//! As elsewhere in this crate, there is no general ABI encoder here. The calldata for
+//! `aggregate` and the layout of its return value are hard-coded for the two deposit contract
+//! calls.
+
+use crate::abi::{word, WORD};
+use crate::{
+    decode::hex_to_address, decode_deposit_count, decode_deposit_root, BlockQuery, Eth1Client,
+    Eth1Error, DEPOSIT_COUNT_FN_SIGNATURE, DEPOSIT_ROOT_FN_SIGNATURE,
+};
+use ethereum_types::H256 as Hash256;
+use std::convert::TryFrom;
+
+/// `keccak("aggregate((address,bytes)[])")[0..4]`
+pub const MULTICALL_AGGREGATE_FN_SIGNATURE: &str = "0x252dba42";
+/// The address of the Multicall3 contract on most networks.
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+impl Eth1Client {
+    /// Returns the deposit root and count of the deposit contract at `address`, read at `block` in
+    /// a single `eth_call` to the Multicall3 contract at `multicall`.
+    ///
+    /// Returns an error if the deposit contract does not exist at `block`.
+    pub async fn get_root_and_count_via_multicall(
+        &self,
+        multicall: &str,
+        address: &str,
+        block: BlockQuery,
+    ) -> Result<(Hash256, u64), Eth1Error> {
+        let calldata = aggregate_calldata(
+            address,
+            &[DEPOSIT_ROOT_FN_SIGNATURE, DEPOSIT_COUNT_FN_SIGNATURE],
+        )?;
+        let bytes = self
+            .call(multicall, &calldata, block)
+            .await?
+            .ok_or("Multicall response was none")?;
+
+        let results = decode_aggregate_result(&bytes)?;
+        if results.len() != 2 {
+            return Err(format!("Multicall returned {} results, expected 2", results.len()).into());
+        }
+        let not_deployed = || format!("No deposit contract at {}", address);
+        let root = decode_deposit_root(&results[0])?.ok_or_else(not_deployed)?;
+        let count = decode_deposit_count(&results[1])?.ok_or_else(not_deployed)?;
+
+        Ok((root, count))
+    }
+}
+
+/// Returns the `0x`-prefixed calldata for `aggregate`, calling each of the `selectors` (which
+/// take no arguments) on `target`.
+fn aggregate_calldata(target: &str, selectors: &[&str]) -> Result<String, String> {
+    let target = hex_to_address(target)?;
+    // Each `(address,bytes)` tuple is an address, an offset to the bytes, the length of the bytes
+    // and a single word for the 4-byte selector.
+    let tuple_len = 4 * WORD;
+
+    let mut data = vec![];
+    // Offset of the array.
+    data.extend_from_slice(&word(WORD as u64));
+    data.extend_from_slice(&word(selectors.len() as u64));
+    // Offsets of each tuple, relative to the first offset.
+    for i in 0..selectors.len() {
+        data.extend_from_slice(&word((selectors.len() * WORD + i * tuple_len) as u64));
+    }
+    for selector in selectors {
+        let selector = hex::decode(selector.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid selector {}: {:?}", selector, e))?;
+        let mut address = [0; WORD];
+        address[12..].copy_from_slice(target.as_bytes());
+        let mut selector_word = [0; WORD];
+        selector_word[..selector.len()].copy_from_slice(&selector);
+
+        data.extend_from_slice(&address);
+        data.extend_from_slice(&word(2 * WORD as u64));
+        data.extend_from_slice(&word(selector.len() as u64));
+        data.extend_from_slice(&selector_word);
+    }
+
+    Ok(format!(
+        "{}{}",
+        MULTICALL_AGGREGATE_FN_SIGNATURE,
+        hex::encode(data)
+    ))
+}
+
+/// Decodes the `(uint256 blockNumber, bytes[] returnData)` returned by `aggregate`, returning
+/// `returnData`.
+///
+/// Every offset and length is read from the response, so each is bounds checked and an
+/// arithmetic overflow is an error rather than a panic.
+fn decode_aggregate_result(bytes: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let array_start = read_usize(bytes, WORD)?;
+    let len = read_usize(bytes, array_start)?;
+    let elements_start = checked_add(array_start, WORD)?;
+
+    (0..len)
+        .map(|i| {
+            let offset = read_usize(bytes, checked_add(elements_start, i * WORD)?)?;
+            let element_start = checked_add(elements_start, offset)?;
+            let element_len = read_usize(bytes, element_start)?;
+            let data_start = checked_add(element_start, WORD)?;
+            bytes
+                .get(data_start..checked_add(data_start, element_len)?)
+                .map(|slice| slice.to_vec())
+                .ok_or_else(|| format!("Multicall result {} is out of bounds", i))
+        })
+        .collect()
+}
+
+/// Reads the big-endian ABI word at `offset` as a `usize`.
+fn read_usize(bytes: &[u8], offset: usize) -> Result<usize, String> {
+    let word = bytes
+        .get(offset..checked_add(offset, WORD)?)
+        .ok_or_else(|| format!("Multicall response too short to read offset {}", offset))?;
+    if word[..WORD - 8].iter().any(|byte| *byte != 0) {
+        return Err(format!("Multicall value at offset {} is too large", offset));
+    }
+    let mut array = [0; 8];
+    array.copy_from_slice(&word[WORD - 8..]);
+    usize::try_from(u64::from_be_bytes(array))
+        .map_err(|e| format!("Multicall value at offset {} is too large: {}", offset, e))
+}
+
+/// Returns `a + b`, or an error if it overflows, e.g., for an offset in a hostile response.
+fn checked_add(a: usize, b: usize) -> Result<usize, String> {
+    a.checked_add(b)
+        .ok_or_else(|| format!("Multicall offset {} + {} overflows", a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::hex_to_bytes;
+
+    const DEPOSIT_CONTRACT: &str = "0x00000000219ab540356cbb839cbe05303d7705fa";
+
+    /// Returns the hex of the ABI `words`, each given as its hex digits without padding.
+    fn words(words: &[&str]) -> String {
+        words.iter().map(|word| format!("{:0>64}", word)).collect()
+    }
+
+    /// The `aggregate` return value for a deposit root of `0x11..11` and a count of `5`, at
+    /// block `0x10`.
+    fn aggregate_result() -> Vec<u8> {
+        let count_le = format!("05{}", "0".repeat(62));
+        let hex = words(&[
+            "10", // blockNumber
+            "40", // offset of returnData
+            "2",  // returnData.length
+            "40", // offset of returnData[0]
+            "80", // offset of returnData[1]
+            "20", // returnData[0].length
+            &"11".repeat(32),
+            "60", // returnData[1].length, the ABI-encoded `bytes` of the count
+            "20",
+            "8",
+            &count_le,
+        ]);
+        hex::decode(hex).unwrap()
+    }
+
+    #[test]
+    fn encodes_aggregate_calldata() {
+        let address = &DEPOSIT_CONTRACT[2..];
+        let expected = format!(
+            "0x252dba42{}",
+            words(&[
+                "20", // offset of the array
+                "2",  // array length
+                "40", // offset of calls[0]
+                "c0", // offset of calls[1]
+                address,
+                "40", // offset of calls[0].callData
+                "4",  // calls[0].callData.length
+                &format!("{:0<64}", "c5f2892f"),
+                address,
+                "40",
+                "4",
+                &format!("{:0<64}", "621fd130"),
+            ])
+        );
+        let calldata = aggregate_calldata(
+            DEPOSIT_CONTRACT,
+            &[DEPOSIT_ROOT_FN_SIGNATURE, DEPOSIT_COUNT_FN_SIGNATURE],
+        )
+        .unwrap();
+        assert_eq!(calldata, expected);
+        assert_eq!(hex_to_bytes(&calldata).unwrap().len(), 4 + 12 * WORD);
+    }
+
+    #[test]
+    fn decodes_aggregate_result() {
+        let results = decode_aggregate_result(&aggregate_result()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            decode_deposit_root(&results[0]).unwrap(),
+            Some(Hash256::repeat_byte(0x11))
+        );
+        assert_eq!(decode_deposit_count(&results[1]).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn rejects_a_truncated_result() {
+        let bytes = aggregate_result();
+        for len in [0, WORD, 3 * WORD, bytes.len() - 1] {
+            assert!(decode_aggregate_result(&bytes[..len]).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_offsets_which_overflow() {
+        let mut bytes = aggregate_result();
+        // Points returnData[1] at `usize::MAX`, relative to the start of the elements.
+        let element_offset = 4 * WORD;
+        bytes[element_offset..element_offset + WORD].copy_from_slice(&[0; WORD]);
+        bytes[element_offset + WORD - 8..element_offset + WORD]
+            .copy_from_slice(&(usize::MAX as u64).to_be_bytes());
+        assert!(decode_aggregate_result(&bytes).is_err());
+
+        // A length of `usize::MAX` for returnData[0].
+        let mut bytes = aggregate_result();
+        let length_offset = 5 * WORD;
+        bytes[length_offset + WORD - 8..length_offset + WORD]
+            .copy_from_slice(&(usize::MAX as u64).to_be_bytes());
+        assert!(decode_aggregate_result(&bytes).is_err());
+
+        // An array length so large that the offsets cannot all be read.
+        let mut bytes = aggregate_result();
+        bytes[2 * WORD + WORD - 8..3 * WORD].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(decode_aggregate_result(&bytes).is_err());
+    }
+}
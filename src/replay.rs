@@ -0,0 +1,67 @@
+//! Submitting deposits to a deposit contract on a local test node (e.g., Anvil, Ganache or
+//! Hardhat), so that the logs read back by this crate can be checked against known deposits.
+
+use crate::client::response_result;
+use crate::{decode, deposit_calldata, deposit_value_wei, DepositData, Eth1Client, Eth1Error};
+use ethereum_types::H256 as Hash256;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Sends a `deposit` transaction from `from` to the deposit contract at `address` for each
+    /// of `deposits`, in order, returning the transaction hashes.
+    ///
+    /// Uses `eth_sendTransaction`, so `from` must be an account which the node signs for, as
+    /// with the development accounts of a local test node. Transactions are sent one at a time
+    /// so that the deposits are made in the order given. Returns an error on the first
+    /// transaction the node rejects; earlier deposits will still be made.
+    pub async fn submit_deposits(
+        &self,
+        from: &str,
+        address: &str,
+        deposits: &[DepositData],
+    ) -> Result<Vec<Hash256>, Eth1Error> {
+        let mut hashes = Vec::with_capacity(deposits.len());
+        for deposit in deposits {
+            let response_body = self
+                .send_rpc_request(
+                    "eth_sendTransaction",
+                    json!([deposit_transaction(from, address, deposit)]),
+                )
+                .await?;
+            let result = response_result(&response_body)?
+                .ok_or("No result was returned for send transaction")?;
+            hashes.push(decode::hash(&result)?);
+        }
+        Ok(hashes)
+    }
+}
+
+/// Returns the `eth_sendTransaction` object which deposits `deposit` from `from` to the deposit
+/// contract at `address`, without sending it.
+///
+/// The amount is sent as the value, in wei. Gas is left for the node to estimate.
+pub fn deposit_transaction(from: &str, address: &str, deposit: &DepositData) -> Value {
+    json!({
+        "from": from,
+        "to": address,
+        "value": format!("{:#x}", deposit_value_wei(deposit.amount)),
+        "data": deposit_calldata(deposit),
+    })
+}
+
+/// Sends a `deposit` transaction from `from` to the deposit contract at `address` for each of
+/// `deposits`, in order, returning the transaction hashes.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn submit_deposits(
+    endpoint: &str,
+    from: &str,
+    address: &str,
+    deposits: &[DepositData],
+    timeout: Duration,
+) -> Result<Vec<Hash256>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .submit_deposits(from, address, deposits)
+        .await
+}
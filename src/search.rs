@@ -0,0 +1,52 @@
+//! Searching the deposit contract logs for particular deposits.
+
+use crate::deposit_log::sort_and_dedup_deposits;
+use crate::{
+    chunk_range, decode_deposit_log_with_topic, DepositLog, Eth1Client, Eth1Error, PublicKey,
+};
+use std::ops::Range;
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Returns all deposits to `address` in `block_height_range` with the given validator
+    /// `pubkey`, including any top-ups, ordered by deposit index.
+    ///
+    /// The range is requested in chunks of `Self::log_chunk_size` blocks.
+    pub async fn find_deposits_by_pubkey(
+        &self,
+        address: &str,
+        pubkey: &PublicKey,
+        block_height_range: Range<u64>,
+    ) -> Result<Vec<DepositLog>, Eth1Error> {
+        let mut deposits = vec![];
+
+        for chunk in chunk_range(block_height_range, self.log_chunk_size()) {
+            for log in self.get_deposit_logs_in_range(address, chunk).await? {
+                let deposit = decode_deposit_log_with_topic(&log, self.deposit_topic())?;
+                if deposit.pubkey == *pubkey {
+                    deposits.push(deposit);
+                }
+            }
+        }
+
+        sort_and_dedup_deposits(&mut deposits);
+
+        Ok(deposits)
+    }
+}
+
+/// Returns all deposits to `address` in `block_height_range` with the given validator
+/// `pubkey`, including any top-ups, ordered by deposit index.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn find_deposits_by_pubkey(
+    endpoint: &str,
+    address: &str,
+    pubkey: &PublicKey,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<Vec<DepositLog>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .find_deposits_by_pubkey(address, pubkey, block_height_range)
+        .await
+}
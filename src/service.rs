@@ -0,0 +1,67 @@
+//! The HTTP transport of an `Eth1Client` as a `tower_service::Service`, so that requests can be
+//! wrapped in middleware from the `tower` ecosystem, e.g., for retries, rate limits or metrics.
+
+use crate::{Eth1Client, Eth1Error};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// A JSON-RPC request to be sent by an `HttpTransport`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RpcRequest {
+    pub method: String,
+    pub params: Value,
+}
+
+impl RpcRequest {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// Sends each `RpcRequest` as a single POST to the endpoint of an `Eth1Client`, returning the
+/// raw bytes of the response body.
+///
+/// Unlike the methods of `Eth1Client`, requests are never retried; each is only bounded by
+/// `Eth1Client::timeout_for` its method. Retries, deadlines and the like are left to whichever
+/// middleware the transport is wrapped in. A response containing a JSON-RPC error object is
+/// returned as an `Eth1Error::Rpc`.
+#[derive(Clone)]
+pub struct HttpTransport {
+    client: Eth1Client,
+}
+
+impl Eth1Client {
+    /// Returns the transport of this client as a `tower_service::Service`, sharing its HTTP
+    /// connection pool and configuration.
+    pub fn transport(&self) -> HttpTransport {
+        HttpTransport {
+            client: self.clone(),
+        }
+    }
+}
+
+impl Service<RpcRequest> for HttpTransport {
+    type Response = Vec<u8>;
+    type Error = Eth1Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Vec<u8>, Eth1Error>> + Send>>;
+
+    /// The transport is always ready; `reqwest` queues requests in its own connection pool.
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Eth1Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RpcRequest) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move {
+            client
+                .send_rpc_request_once(&request.method, request.params)
+                .await
+        })
+    }
+}
@@ -0,0 +1,63 @@
+//! Reading the state of the deposit contract at a single block.
+
+use crate::{BlockQuery, Eth1Client, Eth1Data, Eth1Error};
+use std::time::Duration;
+
+/// The state of the deposit contract at a single block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DepositSnapshot {
+    /// The deposit root, deposit count and hash of the block, as voted on by beacon nodes.
+    pub eth1_data: Eth1Data,
+    pub block_number: u64,
+    pub timestamp: u64,
+}
+
+impl From<DepositSnapshot> for Eth1Data {
+    fn from(snapshot: DepositSnapshot) -> Eth1Data {
+        snapshot.eth1_data
+    }
+}
+
+impl Eth1Client {
+    /// Returns the `Eth1Data` of the deposit contract at `address` for `block`, along with the
+    /// number and timestamp of the block.
+    ///
+    /// The block is resolved first and both contract calls are made at its hash, so the values
+    /// are consistent even if `block` is `BlockQuery::Latest` and the head advances in between,
+    /// and the calls fail rather than reading another block if it is re-orged out.
+    pub async fn snapshot(
+        &self,
+        address: &str,
+        block: BlockQuery,
+    ) -> Result<DepositSnapshot, Eth1Error> {
+        let block = self.get_block(block).await?;
+        let (deposit_root, deposit_count) = self
+            .get_root_and_count(address, BlockQuery::Hash(block.hash))
+            .await?;
+
+        Ok(DepositSnapshot {
+            eth1_data: Eth1Data {
+                deposit_root,
+                deposit_count,
+                block_hash: block.hash,
+            },
+            block_number: block.number,
+            timestamp: block.timestamp,
+        })
+    }
+}
+
+/// Returns the `Eth1Data` of the deposit contract at `address` for `block`, along with the number
+/// and timestamp of the block.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn snapshot(
+    endpoint: &str,
+    address: &str,
+    block: BlockQuery,
+    timeout: Duration,
+) -> Result<DepositSnapshot, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .snapshot(address, block)
+        .await
+}
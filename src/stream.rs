@@ -0,0 +1,78 @@
+//! Fetching deposits, and the history of the deposit root, as a `Stream`, so that they can be
+//! processed as each chunk arrives rather than once the whole range has been fetched.
+
+use crate::deposit_log::sort_and_dedup_deposits;
+use crate::{chunk_range, DepositLog, Eth1Client, Eth1Error};
+use ethereum_types::H256 as Hash256;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::ops::Range;
+
+/// The number of blocks whose deposit root and count are requested in each batch by
+/// `Eth1Client::deposit_root_history`.
+const ROOT_HISTORY_BATCH_BLOCKS: u64 = 50;
+
+impl Eth1Client {
+    /// Returns a stream of the deposits to `address` in `block_height_range`, ordered by deposit
+    /// index.
+    ///
+    /// The range is requested one chunk of `Self::log_chunk_size` blocks at a time, and the next
+    /// chunk is only requested once the deposits of the previous one have been consumed, so
+    /// memory use does not grow with the range. A chunk which fails yields its error in place of
+    /// its deposits.
+    pub fn deposit_stream<'a>(
+        &'a self,
+        address: &'a str,
+        block_height_range: Range<u64>,
+    ) -> impl Stream<Item = Result<DepositLog, Eth1Error>> + 'a {
+        stream::iter(chunk_range(block_height_range, self.log_chunk_size()))
+            .then(move |chunk| self.get_deposits_in_range(address, chunk))
+            .map_ok(|(_, mut deposits)| {
+                sort_and_dedup_deposits(&mut deposits);
+                stream::iter(deposits.into_iter().map(Ok))
+            })
+            .try_flatten()
+    }
+
+    /// Returns a stream of `(block, deposit_root, deposit_count)` of `address` at every
+    /// `interval` blocks of `block_height_range`, starting from its first block, e.g., to audit
+    /// the eth1 data voted on by a beacon chain.
+    ///
+    /// The values are requested with `Self::get_roots_and_counts`, in batches of
+    /// `ROOT_HISTORY_BATCH_BLOCKS` blocks. An `interval` of `0` is treated as `1`. A batch which
+    /// fails, e.g., because the contract was not deployed at one of its blocks, yields its
+    /// error in place of its values.
+    pub fn deposit_root_history<'a>(
+        &'a self,
+        address: &'a str,
+        block_height_range: Range<u64>,
+        interval: u64,
+    ) -> impl Stream<Item = Result<(u64, Hash256, u64), Eth1Error>> + 'a {
+        let interval = interval.max(1);
+        // Each batch starts a whole number of intervals after the start of the range, so that
+        // stepping through each gives the same blocks as stepping through the whole range.
+        let batches = chunk_range(
+            block_height_range,
+            interval.saturating_mul(ROOT_HISTORY_BATCH_BLOCKS),
+        );
+        stream::iter(batches)
+            .then(move |batch| self.root_history_batch(address, batch, interval))
+            .map_ok(|values| stream::iter(values.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Returns the `(block, deposit_root, deposit_count)` at every `interval` blocks of `batch`.
+    async fn root_history_batch(
+        &self,
+        address: &str,
+        batch: Range<u64>,
+        interval: u64,
+    ) -> Result<Vec<(u64, Hash256, u64)>, Eth1Error> {
+        let blocks: Vec<u64> = batch.step_by(interval as usize).collect();
+        let values = self.get_roots_and_counts(address, &blocks).await?;
+        Ok(blocks
+            .into_iter()
+            .zip(values)
+            .map(|(block, (root, count))| (block, root, count))
+            .collect())
+    }
+}
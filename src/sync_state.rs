@@ -0,0 +1,200 @@
+//! Progress of a sync of the deposit contract logs, persisted as JSON so that it can be resumed.
+
+use crate::{CheckError, DepositLog, Eth1Id};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// How far a sync of the deposit logs of `contract` on `network` has progressed.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SyncState {
+    pub contract: String,
+    pub network: Eth1Id,
+    /// The `end` of the last block range whose logs were fetched, `None` if nothing has been
+    /// synced yet.
+    pub last_synced_block: Option<u64>,
+    /// The number of deposits seen so far, i.e., one more than the highest deposit index.
+    ///
+    /// Deposits are recorded without gaps, so this is also the deposit count of the contract at
+    /// `last_synced_block`. A sync which does not start before the first deposit should start
+    /// from the contract's count at the block before it.
+    pub deposit_count: u64,
+}
+
+impl SyncState {
+    /// Returns the state of a sync which has not started yet.
+    pub fn new(contract: &str, network: Eth1Id) -> Self {
+        Self {
+            contract: contract.to_string(),
+            network,
+            last_synced_block: None,
+            deposit_count: 0,
+        }
+    }
+
+    /// Returns the block to resume syncing from, or `start_block` if nothing has been synced.
+    ///
+    /// Ranges are half-open, so this is the `end` of the last synced range. Should a deposit be
+    /// seen twice anyway, it is ignored by `Self::record`.
+    pub fn next_block(&self, start_block: u64) -> u64 {
+        self.last_synced_block.unwrap_or(start_block)
+    }
+
+    /// Records that the logs in `block_height_range` have been fetched, yielding `deposits`.
+    ///
+    /// Deposits which have already been recorded are ignored. Returns an error, without
+    /// recording anything, if a deposit is missing, i.e., the lowest new index is not
+    /// `Self::deposit_count` or the new indices are not consecutive. A chunk of logs dropped or
+    /// pruned by the endpoint is caught here, rather than by a count which only looks at the
+    /// highest index.
+    pub fn record(
+        &mut self,
+        block_height_range: &Range<u64>,
+        deposits: &[DepositLog],
+    ) -> Result<(), CheckError> {
+        let mut indices: Vec<u64> = deposits
+            .iter()
+            .map(|deposit| deposit.index)
+            .filter(|index| *index >= self.deposit_count)
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut count = self.deposit_count;
+        for index in indices {
+            if index != count {
+                return Err(CheckError::DepositGap {
+                    expected: count,
+                    found: index,
+                });
+            }
+            count += 1;
+        }
+        self.deposit_count = count;
+        self.last_synced_block = Some(block_height_range.end);
+        Ok(())
+    }
+
+    /// Returns an error if this state is for a different `contract` or `network`.
+    ///
+    /// Contract addresses are compared case-insensitively, so checksummed and lower-case
+    /// addresses match.
+    pub fn validate(&self, contract: &str, network: &Eth1Id) -> Result<(), String> {
+        if !self.contract.eq_ignore_ascii_case(contract) {
+            Err(format!(
+                "Sync state is for contract {}, not {}",
+                self.contract, contract
+            ))
+        } else if self.network != *network {
+            Err(format!(
+                "Sync state is for network {:?}, not {:?}",
+                self.network, network
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads the state from the JSON file at `path`, returning `Ok(None)` if it does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read sync state {}: {}", path.display(), e))?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse sync state {}: {}", path.display(), e))
+    }
+
+    /// Writes the state as JSON to `path`.
+    ///
+    /// The state is written to a temporary file which is then renamed over `path`, so an
+    /// interrupted write does not leave a truncated file behind.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| format!("Failed to serialize sync state: {}", e))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)
+            .map_err(|e| format!("Failed to write sync state {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, path)
+            .map_err(|e| format!("Failed to write sync state {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deposit;
+
+    fn state() -> SyncState {
+        SyncState::new(
+            "0x00000000219ab540356cbb839cbe05303d7705fa",
+            Eth1Id::Mainnet,
+        )
+    }
+
+    #[test]
+    fn records_consecutive_deposits() {
+        let mut state = state();
+        state
+            .record(&(0..10), &[deposit(0, 1), deposit(1, 5)])
+            .unwrap();
+        state.record(&(10..20), &[]).unwrap();
+        state.record(&(20..30), &[deposit(2, 25)]).unwrap();
+        assert_eq!(state.deposit_count, 3);
+        assert_eq!(state.last_synced_block, Some(30));
+        assert_eq!(state.next_block(0), 30);
+    }
+
+    #[test]
+    fn ignores_deposits_seen_twice() {
+        let mut state = state();
+        state
+            .record(&(0..10), &[deposit(0, 1), deposit(0, 1), deposit(1, 5)])
+            .unwrap();
+        state
+            .record(&(10..20), &[deposit(1, 5), deposit(2, 15)])
+            .unwrap();
+        assert_eq!(state.deposit_count, 3);
+    }
+
+    #[test]
+    fn rejects_a_dropped_chunk() {
+        let mut state = state();
+        state.record(&(0..10), &[deposit(0, 1)]).unwrap();
+        // The chunk 10..20, holding deposit 1, was lost.
+        assert_eq!(
+            state.record(&(20..30), &[deposit(2, 25)]),
+            Err(CheckError::DepositGap {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(state.deposit_count, 1);
+        assert_eq!(state.last_synced_block, Some(10));
+    }
+
+    #[test]
+    fn rejects_a_gap_within_a_chunk() {
+        let mut state = state();
+        assert_eq!(
+            state.record(&(0..10), &[deposit(0, 1), deposit(2, 3)]),
+            Err(CheckError::DepositGap {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(state.deposit_count, 0);
+        assert_eq!(state.last_synced_block, None);
+    }
+
+    #[test]
+    fn starts_from_an_existing_count() {
+        let mut state = state();
+        state.deposit_count = 100;
+        state.record(&(50..60), &[deposit(100, 55)]).unwrap();
+        assert_eq!(state.deposit_count, 101);
+    }
+}
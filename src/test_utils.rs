@@ -0,0 +1,76 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{DepositLog, PublicKey, Signature, WithdrawalCredentials, DEPOSIT_EVENT_TOPIC};
+use ethereum_types::{H160, H256 as Hash256};
+use serde_json::{json, Value};
+
+/// Returns a 32 ETH deposit with the given `index`, included in `block_number`.
+pub(crate) fn deposit(index: u64, block_number: u64) -> DepositLog {
+    DepositLog {
+        pubkey: PublicKey([0xaa; 48]),
+        withdrawal_credentials: WithdrawalCredentials([0xbb; 32]),
+        amount: 32_000_000_000,
+        signature: Signature([0xcc; 96]),
+        index,
+        address: H160::zero(),
+        block_number,
+        transaction_hash: Hash256::from_low_u64_be(index),
+        removed: false,
+        deposited_at: None,
+    }
+}
+
+/// Returns the hex-encoded data of a `DepositEvent` log for `deposit`.
+pub(crate) fn deposit_log_data(deposit: &DepositLog) -> String {
+    let mut data = vec![0u8; 576];
+    // Offsets of the five dynamic `bytes` values.
+    for (i, offset) in [160u64, 256, 320, 384, 512].iter().enumerate() {
+        data[i * 32 + 24..i * 32 + 32].copy_from_slice(&offset.to_be_bytes());
+    }
+    // Lengths of each value.
+    for (position, length) in [(160, 48u64), (256, 32), (320, 8), (384, 96), (512, 8)].iter() {
+        data[position + 24..position + 32].copy_from_slice(&length.to_be_bytes());
+    }
+    data[192..240].copy_from_slice(deposit.pubkey.as_bytes());
+    data[288..320].copy_from_slice(deposit.withdrawal_credentials.as_bytes());
+    data[352..360].copy_from_slice(&deposit.amount.to_le_bytes());
+    data[416..512].copy_from_slice(deposit.signature.as_bytes());
+    data[544..552].copy_from_slice(&deposit.index.to_le_bytes());
+    format!("0x{}", hex::encode(data))
+}
+
+/// Returns the JSON of the `DepositEvent` log for `deposit`, at `log_index` in its block.
+pub(crate) fn deposit_log_json(deposit: &DepositLog, log_index: u64) -> Value {
+    json!({
+        "address": format!("{:?}", deposit.address),
+        "topics": [DEPOSIT_EVENT_TOPIC],
+        "data": deposit_log_data(deposit),
+        "blockNumber": format!("0x{:x}", deposit.block_number),
+        "transactionHash": format!("{:?}", deposit.transaction_hash),
+        "transactionIndex": "0x0",
+        "blockHash": format!("0x{:064x}", deposit.block_number),
+        "logIndex": format!("0x{:x}", log_index),
+        "removed": deposit.removed,
+    })
+}
+
+/// Returns an `eth_getLogs` response body with the given `logs` as its result.
+pub(crate) fn logs_response(logs: Vec<Value>) -> Vec<u8> {
+    json!({ "jsonrpc": "2.0", "id": 1, "result": logs })
+        .to_string()
+        .into_bytes()
+}
+
+/// The `deposit_message_root` of the entry in `DEPOSIT_DATA_FILE`.
+pub(crate) const DEPOSIT_MESSAGE_ROOT: &str =
+    "92e72502b27cd15ac59a1414ad543e64441f30a25c37c03b6cd65fe080269f83";
+/// The `deposit_data_root` of the entry in `DEPOSIT_DATA_FILE`.
+pub(crate) const DEPOSIT_DATA_ROOT: &str =
+    "f976a30df487c4a3e0fa848a20597b2441f865669f70a18d9b07cefa6e510311";
+
+/// A `deposit_data-*.json` file of one 32 ETH mainnet deposit, in the format written by the
+/// staking deposit CLI, including its `deposit_cli_version` field.
+///
+/// The roots were computed from the spec's `hash_tree_root` by an implementation independent of
+/// this crate, so that they check `DepositData::tree_hash_root` rather than restate it.
+pub(crate) const DEPOSIT_DATA_FILE: &str = r#"[{"pubkey": "a10b30557a9fc4e90e33587da2c7ec11365b80a5caef14395e83a8cdf2173c6186abd0f51a3f6489aed3f81d42678cb1", "withdrawal_credentials": "00073c71a6db10457aafe4194e83b8ed22578cc1f62b6095caff34699ed3083d", "amount": 32000000000, "signature": "8c03203d5a7794b1ceeb0825425f7c99b6d3f00d2a4764819ebbd8f5122f4c6986a3c0ddfa1734516e8ba8c5e2ff1c39567390adcae704213e5b7895b2cfec092643607d9ab7d4f10e2b4865829fbcd9f613304d6a87a4c1defb1835526f8ca9", "deposit_message_root": "92e72502b27cd15ac59a1414ad543e64441f30a25c37c03b6cd65fe080269f83", "deposit_data_root": "f976a30df487c4a3e0fa848a20597b2441f865669f70a18d9b07cefa6e510311", "fork_version": "00000000", "network_name": "mainnet", "deposit_cli_version": "2.7.0"}]"#;
@@ -0,0 +1,138 @@
+//! Finding blocks by their timestamp, and the timestamps of the blocks containing deposits.
+
+use crate::{Block, BlockQuery, DepositLog, Eth1Client, Eth1Error};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::time::Duration;
+
+/// The number of blocks requested in each batch by `Eth1Client::attach_timestamps`.
+const TIMESTAMP_BATCH_BLOCKS: usize = 100;
+
+impl Eth1Client {
+    /// Returns the number of the first block with a timestamp at or after `timestamp` (in unix
+    /// seconds), or one past the head if there is no such block.
+    ///
+    /// Block timestamps are strictly increasing, so this is a binary search, requesting about
+    /// `log2(head)` blocks.
+    pub async fn find_block_by_timestamp(&self, timestamp: u64) -> Result<u64, Eth1Error> {
+        let head = self.get_block(BlockQuery::Latest).await?;
+        if head.timestamp < timestamp {
+            return Ok(head.number + 1);
+        }
+
+        let mut low = 0;
+        let mut high = head.number;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.get_block(BlockQuery::Number(mid)).await?.timestamp < timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low)
+    }
+
+    /// Sets the `deposited_at` of each of `deposits` to the timestamp of its block.
+    ///
+    /// Each distinct block is requested once, in JSON-RPC batches of `TIMESTAMP_BATCH_BLOCKS`,
+    /// falling back to one request per block if the endpoint does not support batches.
+    pub async fn attach_timestamps(&self, deposits: &mut [DepositLog]) -> Result<(), Eth1Error> {
+        let blocks: Vec<u64> = deposits
+            .iter()
+            .map(|deposit| deposit.block_number)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut timestamps = HashMap::with_capacity(blocks.len());
+        for batch in blocks.chunks(TIMESTAMP_BATCH_BLOCKS) {
+            for block in self.get_blocks(batch).await? {
+                timestamps.insert(block.number, block.timestamp);
+            }
+        }
+
+        for deposit in deposits {
+            let timestamp = timestamps.get(&deposit.block_number).ok_or_else(|| {
+                Eth1Error::InvalidResponse(format!(
+                    "No block {} was returned",
+                    deposit.block_number
+                ))
+            })?;
+            deposit.deposited_at = Some(*timestamp);
+        }
+        Ok(())
+    }
+
+    /// As per `Self::get_deposits_concurrently` with a `concurrency` of `1`, but with the
+    /// `deposited_at` of each deposit attached, see `Self::attach_timestamps`.
+    pub async fn get_deposits_with_timestamps(
+        &self,
+        address: &str,
+        block_height_range: Range<u64>,
+    ) -> Result<Vec<DepositLog>, Eth1Error> {
+        let mut deposits = self
+            .get_deposits_concurrently(address, block_height_range, 1)
+            .await?;
+        self.attach_timestamps(&mut deposits).await?;
+        Ok(deposits)
+    }
+
+    /// Returns the blocks with the given `numbers` using a single JSON-RPC batch, or one
+    /// request per block if the endpoint does not support batches.
+    async fn get_blocks(&self, numbers: &[u64]) -> Result<Vec<Block>, Eth1Error> {
+        let params = numbers
+            .iter()
+            .map(|number| json!([format!("0x{:x}", number), false]))
+            .collect();
+        match self.send_rpc_batch("eth_getBlockByNumber", params).await {
+            Ok(results) => results
+                .into_iter()
+                .zip(numbers)
+                .map(|(result, number)| {
+                    let block = result?
+                        .ok_or_else(|| format!("No result was returned for block {}", number))?;
+                    Ok(Block::from_value(&block)?)
+                })
+                .collect(),
+            Err(_) => {
+                let mut blocks = Vec::with_capacity(numbers.len());
+                for number in numbers {
+                    blocks.push(self.get_block(BlockQuery::Number(*number)).await?);
+                }
+                Ok(blocks)
+            }
+        }
+    }
+}
+
+/// Returns the number of the first block with a timestamp at or after `timestamp` (in unix
+/// seconds), or one past the head if there is no such block.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn find_block_by_timestamp(
+    endpoint: &str,
+    timestamp: u64,
+    timeout: Duration,
+) -> Result<u64, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .find_block_by_timestamp(timestamp)
+        .await
+}
+
+/// Returns the deposits to `address` in `block_height_range`, ordered by deposit index, each with
+/// the timestamp of its block.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_deposits_with_timestamps(
+    endpoint: &str,
+    address: &str,
+    block_height_range: Range<u64>,
+    timeout: Duration,
+) -> Result<Vec<DepositLog>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_deposits_with_timestamps(address, block_height_range)
+        .await
+}
@@ -0,0 +1,75 @@
+//! Retrieval of transactions, e.g., to find the sender of a deposit.
+
+use crate::client::response_result;
+use crate::decode::{self, optional, required};
+use crate::{Eth1Client, Eth1Error};
+use ethereum_types::{H160, H256 as Hash256, U256};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// A reduced set of fields from an Eth1 transaction.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Transaction {
+    pub hash: Hash256,
+    pub from: H160,
+    /// `None` for contract creation transactions.
+    pub to: Option<H160>,
+    /// The value transferred, in wei.
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub block_number: u64,
+}
+
+impl Transaction {
+    /// Parses a transaction object, returning `None` if it has not been included in a block.
+    fn from_value(value: &Value) -> Result<Option<Self>, String> {
+        let block_number = match optional(value, "blockNumber") {
+            Some(block_number) => decode::quantity(block_number)?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            hash: decode::hash(required(value, "hash")?)?,
+            from: decode::address(required(value, "from")?)?,
+            to: optional(value, "to").map(decode::address).transpose()?,
+            value: decode::quantity_u256(required(value, "value")?)?,
+            input: decode::bytes(required(value, "input")?)?,
+            block_number,
+        }))
+    }
+}
+
+impl Eth1Client {
+    /// Returns the transaction with the given `hash`.
+    ///
+    /// Returns `Ok(None)` if the transaction is unknown or still pending.
+    pub async fn get_transaction_by_hash(
+        &self,
+        hash: Hash256,
+    ) -> Result<Option<Transaction>, Eth1Error> {
+        let response_body = self
+            .send_rpc_request("eth_getTransactionByHash", json!([hash]))
+            .await?;
+        match response_result(&response_body)? {
+            None | Some(Value::Null) => Ok(None),
+            Some(transaction) => Transaction::from_value(&transaction).map_err(|e| {
+                Eth1Error::InvalidResponse(format!("Failed to get transaction {:?}: {}", hash, e))
+            }),
+        }
+    }
+}
+
+/// Returns the transaction with the given `hash`.
+///
+/// Returns `Ok(None)` if the transaction is unknown or still pending.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_transaction_by_hash(
+    endpoint: &str,
+    hash: Hash256,
+    timeout: Duration,
+) -> Result<Option<Transaction>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_transaction_by_hash(hash)
+        .await
+}
@@ -0,0 +1,54 @@
+//! Minimal SSZ merkleization, sufficient for the fixed-size containers used by this crate.
+
+use ethereum_types::H256 as Hash256;
+use sha2::{Digest, Sha256};
+
+/// Number of bytes in a merkle tree chunk.
+pub const BYTES_PER_CHUNK: usize = 32;
+
+/// Returns `sha256(left ++ right)`.
+pub fn hash32_concat(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    let mut output = [0; 32];
+    output.copy_from_slice(&hasher.finalize());
+    output
+}
+
+/// Splits `bytes` into 32-byte chunks, zero-padding the final chunk.
+pub fn pack(bytes: &[u8]) -> Vec<[u8; 32]> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|slice| {
+            let mut chunk = [0; 32];
+            chunk[..slice.len()].copy_from_slice(slice);
+            chunk
+        })
+        .collect()
+}
+
+/// Returns the merkle root of `chunks`, padding with zero chunks to the next power of two.
+pub fn merkleize(chunks: &[[u8; 32]]) -> Hash256 {
+    let mut layer = chunks.to_vec();
+    if layer.is_empty() {
+        layer.push([0; 32]);
+    }
+    layer.resize(layer.len().next_power_of_two(), [0; 32]);
+
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash32_concat(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    Hash256::from_slice(&layer[0])
+}
+
+/// Returns the hash tree root of a `u64`.
+pub fn u64_chunk(value: u64) -> [u8; 32] {
+    let mut chunk = [0; 32];
+    chunk[0..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
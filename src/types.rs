@@ -0,0 +1,227 @@
+//! Fixed-length byte strings found in deposits, e.g., BLS public keys.
+//!
+//! Each is displayed, parsed and serialized as a `0x`-prefixed hex string.
+
+use crate::decode::hex_to_bytes;
+use crate::deposit_log::{CREDS_LEN, PUBKEY_LEN, SIG_LEN};
+use ethereum_types::H160;
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Implements `from_slice`, hex `Display`/`FromStr` and hex serde for a newtype around a byte
+/// array.
+macro_rules! fixed_bytes {
+    ($name:ident, $len:expr, $description:expr) => {
+        impl $name {
+            /// Returns an error if `bytes` is not exactly the right length.
+            pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+                if bytes.len() == $len {
+                    let mut array = [0; $len];
+                    array.copy_from_slice(bytes);
+                    Ok(Self(array))
+                } else {
+                    Err(format!(
+                        "{} was {} bytes, expected {}",
+                        $description,
+                        bytes.len(),
+                        $len
+                    ))
+                }
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "0x{}", hex::encode(&self.0[..]))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            /// Parses a `0x`-prefixed hex string.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_slice(&hex_to_bytes(s)?)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+/// A 48-byte BLS public key, identifying a validator.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct PublicKey(pub [u8; PUBKEY_LEN]);
+
+fixed_bytes!(PublicKey, PUBKEY_LEN, "Pubkey");
+
+/// A 96-byte BLS signature over a deposit message.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Signature(pub [u8; SIG_LEN]);
+
+fixed_bytes!(Signature, SIG_LEN, "Signature");
+
+/// Prefix of withdrawal credentials which commit to a BLS withdrawal key.
+pub const BLS_WITHDRAWAL_PREFIX: u8 = 0x00;
+/// Prefix of withdrawal credentials which withdraw to an eth1 address.
+pub const ETH1_ADDRESS_WITHDRAWAL_PREFIX: u8 = 0x01;
+/// Prefix of withdrawal credentials which withdraw to an eth1 address and allow compounding.
+pub const COMPOUNDING_WITHDRAWAL_PREFIX: u8 = 0x02;
+
+/// The 32-byte withdrawal credentials of a deposit.
+///
+/// The first byte is a prefix which determines how the remaining bytes are interpreted.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct WithdrawalCredentials(pub [u8; CREDS_LEN]);
+
+fixed_bytes!(WithdrawalCredentials, CREDS_LEN, "Withdrawal credentials");
+
+impl WithdrawalCredentials {
+    /// Returns the prefix byte, e.g., `ETH1_ADDRESS_WITHDRAWAL_PREFIX`.
+    pub fn prefix(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns the address that the credentials withdraw to, or `None` if they have a prefix
+    /// other than `ETH1_ADDRESS_WITHDRAWAL_PREFIX` or `COMPOUNDING_WITHDRAWAL_PREFIX`.
+    ///
+    /// The address is the last 20 bytes; the 11 bytes between the prefix and address are
+    /// ignored.
+    pub fn eth1_address(&self) -> Option<H160> {
+        match self.prefix() {
+            ETH1_ADDRESS_WITHDRAWAL_PREFIX | COMPOUNDING_WITHDRAWAL_PREFIX => {
+                Some(H160::from_slice(&self.0[CREDS_LEN - 20..]))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials(prefix: u8) -> WithdrawalCredentials {
+        let mut bytes = [0xbb; CREDS_LEN];
+        bytes[0] = prefix;
+        bytes[CREDS_LEN - 20..].copy_from_slice(&[0xdd; 20]);
+        WithdrawalCredentials(bytes)
+    }
+
+    #[test]
+    fn parses_prefixed_hex() {
+        let hex = format!("0x{}", "aa".repeat(PUBKEY_LEN));
+        assert_eq!(hex.parse(), Ok(PublicKey([0xaa; PUBKEY_LEN])));
+        assert_eq!(
+            format!("0x{}", "AA".repeat(PUBKEY_LEN)).parse(),
+            Ok(PublicKey([0xaa; PUBKEY_LEN]))
+        );
+    }
+
+    #[test]
+    fn rejects_unprefixed_hex() {
+        assert!("aa".repeat(PUBKEY_LEN).parse::<PublicKey>().is_err());
+    }
+
+    #[test]
+    fn rejects_the_wrong_length() {
+        assert_eq!(
+            format!("0x{}", "aa".repeat(PUBKEY_LEN - 1)).parse::<PublicKey>(),
+            Err("Pubkey was 47 bytes, expected 48".to_string())
+        );
+        assert!(format!("0x{}", "cc".repeat(SIG_LEN + 1))
+            .parse::<Signature>()
+            .is_err());
+        assert!("0x".parse::<WithdrawalCredentials>().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        for hex in [
+            format!("0x{}", "zz".repeat(PUBKEY_LEN)),
+            format!("0x{}a", "aa".repeat(PUBKEY_LEN)),
+        ] {
+            assert!(hex.parse::<PublicKey>().is_err(), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn displays_prefixed_lowercase_hex() {
+        let pubkey = PublicKey([0xab; PUBKEY_LEN]);
+        assert_eq!(pubkey.to_string(), format!("0x{}", "ab".repeat(PUBKEY_LEN)));
+        assert_eq!(format!("{:?}", pubkey), pubkey.to_string());
+        assert_eq!(
+            Signature([0xcd; SIG_LEN]).to_string(),
+            format!("0x{}", "cd".repeat(SIG_LEN))
+        );
+    }
+
+    #[test]
+    fn serde_round_trips() {
+        let pubkey = PublicKey([0xaa; PUBKEY_LEN]);
+        let signature = Signature([0xcc; SIG_LEN]);
+        let credentials = credentials(ETH1_ADDRESS_WITHDRAWAL_PREFIX);
+
+        let json = serde_json::to_string(&pubkey).unwrap();
+        assert_eq!(json, format!("\"{}\"", pubkey));
+        assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), pubkey);
+        let json = serde_json::to_string(&signature).unwrap();
+        assert_eq!(serde_json::from_str::<Signature>(&json).unwrap(), signature);
+        let json = serde_json::to_string(&credentials).unwrap();
+        assert_eq!(
+            serde_json::from_str::<WithdrawalCredentials>(&json).unwrap(),
+            credentials
+        );
+
+        assert!(serde_json::from_str::<PublicKey>("\"0xaa\"").is_err());
+        assert!(serde_json::from_str::<PublicKey>("48").is_err());
+    }
+
+    #[test]
+    fn bls_credentials_have_no_address() {
+        let credentials = credentials(BLS_WITHDRAWAL_PREFIX);
+        assert_eq!(credentials.prefix(), 0x00);
+        assert_eq!(credentials.eth1_address(), None);
+    }
+
+    #[test]
+    fn eth1_and_compounding_credentials_withdraw_to_the_last_20_bytes() {
+        for prefix in [
+            ETH1_ADDRESS_WITHDRAWAL_PREFIX,
+            COMPOUNDING_WITHDRAWAL_PREFIX,
+        ] {
+            let credentials = credentials(prefix);
+            assert_eq!(credentials.prefix(), prefix);
+            assert_eq!(credentials.eth1_address(), Some(H160::repeat_byte(0xdd)));
+        }
+    }
+
+    #[test]
+    fn unknown_prefixes_have_no_address() {
+        assert_eq!(credentials(0x03).eth1_address(), None);
+    }
+}
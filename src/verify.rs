@@ -0,0 +1,157 @@
+//! Checking the entries of a `deposit_data-*.json` file against the deposits made on chain.
+
+use crate::{
+    parse_deposit_data, DepositDataJson, DepositLog, Eth1Client, Eth1Error, FieldMismatch,
+    PublicKey, VerifyFileError,
+};
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The outcome of looking for the on-chain deposit of one entry of a deposit data file.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DepositVerification {
+    /// A deposit matches the entry in every field.
+    Found { index: u64, block_number: u64 },
+    /// Deposits were made for the pubkey, but none match the entry. Reports the deposit with
+    /// the fewest differing fields, with the expected and on-chain value of each.
+    ///
+    /// Differing `withdrawal_credentials` deserve the most attention, since the deposit's funds
+    /// can only be withdrawn with the credentials on chain.
+    Mismatch {
+        index: u64,
+        fields: Vec<FieldMismatch>,
+    },
+    /// No deposit has been made for the pubkey.
+    NotFound,
+}
+
+/// The outcome for one entry of a deposit data file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntryVerification {
+    pub pubkey: PublicKey,
+    pub result: DepositVerification,
+}
+
+/// The outcome for each entry of a deposit data file, in the order of the file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VerifyReport {
+    pub entries: Vec<EntryVerification>,
+}
+
+impl VerifyReport {
+    /// Returns `true` if every entry has a matching deposit.
+    pub fn is_ok(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| matches!(entry.result, DepositVerification::Found { .. }))
+    }
+}
+
+impl Eth1Client {
+    /// Looks for a deposit to `address` matching each of `entries`, i.e., with the same pubkey,
+    /// withdrawal credentials, amount, signature and deposit data root.
+    ///
+    /// Scans the logs from `from_block` to the head once, keeping only the deposits for the
+    /// pubkeys of `entries`. If `from_block` is `None`, the scan starts at the deployment of the
+    /// contract, which is found with `Self::find_deployment_block` and so needs an archive node.
+    pub async fn verify_deposit_data(
+        &self,
+        address: &str,
+        entries: &[DepositDataJson],
+        from_block: Option<u64>,
+    ) -> Result<VerifyReport, Eth1Error> {
+        let pubkeys = entries
+            .iter()
+            .map(|entry| {
+                format!("0x{}", entry.pubkey)
+                    .parse::<PublicKey>()
+                    .map_err(|e| Eth1Error::InvalidRequest(format!("Invalid pubkey: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut deposits: HashMap<PublicKey, Vec<DepositLog>> =
+            pubkeys.iter().map(|pubkey| (*pubkey, vec![])).collect();
+
+        let from_block = match from_block {
+            Some(block) => block,
+            None => self.find_deployment_block(address).await?,
+        };
+        let head = self.get_block_number().await?;
+        let mut stream = Box::pin(self.deposit_stream(address, from_block..head + 1));
+        while let Some(deposit) = stream.try_next().await? {
+            if let Some(matching) = deposits.get_mut(&deposit.pubkey) {
+                matching.push(deposit);
+            }
+        }
+
+        let entries = entries
+            .iter()
+            .zip(pubkeys)
+            .map(|(entry, pubkey)| {
+                let candidates = deposits.get(&pubkey).map(Vec::as_slice).unwrap_or(&[]);
+                let result = candidates
+                    .iter()
+                    .map(|deposit| (deposit, entry.mismatches(deposit)))
+                    .min_by_key(|(_, fields)| fields.len())
+                    .map_or(DepositVerification::NotFound, |(deposit, fields)| {
+                        if fields.is_empty() {
+                            DepositVerification::Found {
+                                index: deposit.index,
+                                block_number: deposit.block_number,
+                            }
+                        } else {
+                            DepositVerification::Mismatch {
+                                index: deposit.index,
+                                fields,
+                            }
+                        }
+                    });
+                EntryVerification { pubkey, result }
+            })
+            .collect();
+        Ok(VerifyReport { entries })
+    }
+}
+
+/// Looks for a deposit to `contract` matching each entry of the `deposit_data-*.json` file at
+/// `path`, in the blocks from `from_block` to the head.
+///
+/// If `from_block` is `None`, the search starts at the deployment of the contract, which needs
+/// an archive node, see `Eth1Client::verify_deposit_data`. Returns `VerifyFileError::Io` if the file cannot be read, and
+/// `VerifyFileError::InvalidFile` if it cannot be parsed.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn verify_deposit_file(
+    path: &Path,
+    endpoint: &str,
+    contract: &str,
+    from_block: Option<u64>,
+    timeout: Duration,
+) -> Result<VerifyReport, VerifyFileError> {
+    let json = fs::read_to_string(path).map_err(VerifyFileError::Io)?;
+    let entries = parse_deposit_data(&json).map_err(VerifyFileError::InvalidFile)?;
+    let report = Eth1Client::new(endpoint, timeout)
+        .verify_deposit_data(contract, &entries, from_block)
+        .await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn missing_file_is_an_io_error() {
+        let result = verify_deposit_file(
+            Path::new("/nonexistent/deposit_data.json"),
+            "http://localhost:8545",
+            "0x0000000000000000000000000000000000000000",
+            None,
+            Duration::from_secs(1),
+        )
+        .await;
+        assert!(matches!(result, Err(VerifyFileError::Io(_))));
+    }
+}
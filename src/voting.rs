@@ -0,0 +1,47 @@
+//! The range of eth1 blocks which beacon nodes may vote for in an eth1 voting period.
+
+use crate::{BlockQuery, Eth1Client, Eth1Error};
+use std::ops::Range;
+use std::time::Duration;
+
+impl Eth1Client {
+    /// Returns the blocks which are candidates for `Eth1Data` votes in the current voting period
+    /// of a beacon chain with the given `genesis_time`.
+    ///
+    /// As per the honest validator spec, a block is a candidate if its timestamp is between
+    /// `SECONDS_PER_ETH1_BLOCK * ETH1_FOLLOW_DISTANCE` and twice that before the start of the
+    /// voting period. The current period is the one containing the timestamp of the head.
+    pub async fn get_voting_block_range(&self, genesis_time: u64) -> Result<Range<u64>, Eth1Error> {
+        let params = self.network_params();
+        let head = self.get_block(BlockQuery::Latest).await?;
+
+        let period = params.eth1_voting_period_seconds.max(1);
+        let since_genesis = head.timestamp.saturating_sub(genesis_time);
+        let period_start = genesis_time + since_genesis - since_genesis % period;
+
+        let follow_seconds = params.seconds_per_eth1_block * self.follow_distance();
+        let start = self
+            .find_block_by_timestamp(period_start.saturating_sub(follow_seconds * 2))
+            .await?;
+        // One past the last block with `timestamp + follow_seconds <= period_start`.
+        let end = self
+            .find_block_by_timestamp(period_start.saturating_sub(follow_seconds) + 1)
+            .await?;
+
+        Ok(start..end)
+    }
+}
+
+/// Returns the blocks which are candidates for `Eth1Data` votes in the current voting period
+/// of a beacon chain with the given `genesis_time`.
+///
+/// Uses HTTP JSON RPC at `endpoint`. E.g., `http://localhost:8545`.
+pub async fn get_voting_block_range(
+    endpoint: &str,
+    genesis_time: u64,
+    timeout: Duration,
+) -> Result<Range<u64>, Eth1Error> {
+    Eth1Client::new(endpoint, timeout)
+        .get_voting_block_range(genesis_time)
+        .await
+}